@@ -1,9 +1,12 @@
 // Copyright (C) 2024-2025 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
+use anyhow::bail;
 use anyhow::ensure;
 use anyhow::Context as _;
 use anyhow::Error;
@@ -13,6 +16,15 @@ use clap::Args as Arguments;
 use clap::Parser;
 use clap::Subcommand;
 
+use shave::Action;
+use shave::Bounds;
+use shave::ColorScheme;
+use shave::Geolocation;
+use shave::MediaType;
+use shave::Proxy;
+use shave::Region;
+use shave::ResourceType;
+
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Output {
@@ -34,6 +46,36 @@ impl FromStr for Output {
 }
 
 
+/// A rule describing requests to block.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Block {
+  /// Block all resources of the given type.
+  ResourceType(ResourceType),
+  /// Block all requests to URLs matching the given wildcard pattern.
+  Pattern(String),
+  /// Block all requests to URLs matching the given regular expression.
+  Regex(String),
+}
+
+impl FromStr for Block {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if let Some(pattern) = s.strip_prefix("url:") {
+      Ok(Block::Pattern(pattern.to_string()))
+    } else if let Some(regex) = s.strip_prefix("regex:") {
+      Ok(Block::Regex(regex.to_string()))
+    } else {
+      ResourceType::from_str(s)
+        .map(Block::ResourceType)
+        .with_context(|| {
+          format!("`{s}` is neither a resource type nor a `url:` or `regex:` pattern")
+        })
+    }
+  }
+}
+
+
 /// Parse a window size specification from a string.
 fn parse_window_size(s: &str) -> Result<(usize, usize)> {
   let mut it = s.split(&['x', ',', ' ']);
@@ -57,6 +99,213 @@ fn parse_window_size(s: &str) -> Result<(usize, usize)> {
 }
 
 
+/// Parse a duration specification from a string.
+///
+/// Supported units are `ms`, `s`, and `m`. A number without unit is
+/// interpreted as seconds.
+fn parse_duration(s: &str) -> Result<Duration> {
+  let (num_str, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+    Some(idx) => s.split_at(idx),
+    None => (s, "s"),
+  };
+
+  let num = u64::from_str(num_str)
+    .with_context(|| format!("failed to parse duration string `{num_str}` as number"))?;
+  let duration = match unit.trim() {
+    "ms" => Duration::from_millis(num),
+    "s" => Duration::from_secs(num),
+    "m" => num
+      .checked_mul(60)
+      .map(Duration::from_secs)
+      .with_context(|| format!("duration `{s}` is too large"))?,
+    unit => bail!("encountered unsupported duration unit `{unit}`"),
+  };
+  Ok(duration)
+}
+
+
+/// Parse an HTTP header specification of the form `Name: value`.
+fn parse_header(s: &str) -> Result<(String, String)> {
+  let (name, value) = s
+    .split_once(':')
+    .with_context(|| format!("header `{s}` is not of the form `Name: value`"))?;
+  let name = name.trim();
+  ensure!(!name.is_empty(), "header `{s}` is missing a name");
+  Ok((name.to_string(), value.trim().to_string()))
+}
+
+/// Parse HTTP basic authentication credentials of the form
+/// `user:password`.
+fn parse_basic_auth(s: &str) -> Result<(String, String)> {
+  let (user, password) = s
+    .split_once(':')
+    .context("credentials are not of the form `user:password`")?;
+  Ok((user.to_string(), password.to_string()))
+}
+
+/// Parse an HTTP status code or an inclusive range thereof (e.g.,
+/// `404` or `200-299`).
+fn parse_status_range(s: &str) -> Result<RangeInclusive<u16>> {
+  let (start_str, end_str) = s.split_once('-').unwrap_or((s, s));
+  let start = u16::from_str(start_str)
+    .with_context(|| format!("failed to parse status code `{start_str}`"))?;
+  let end =
+    u16::from_str(end_str).with_context(|| format!("failed to parse status code `{end_str}`"))?;
+  ensure!(start <= end, "status code range `{s}` is empty");
+  Ok(start..=end)
+}
+
+/// Parse an interaction of the form `kind:argument` (e.g.,
+/// `click:#accept`).
+///
+/// Typing and selecting take both a selector and a value, separated by
+/// `=>` (e.g., `type:input[name=q]=>shoes`). Whitespace surrounding
+/// arguments is ignored, except for the text to type, which is taken
+/// verbatim.
+pub(crate) fn parse_action(s: &str) -> Result<Action> {
+  let (kind, raw_arg) = s
+    .split_once(':')
+    .with_context(|| format!("action `{s}` is not of the form `kind:argument`"))?;
+  let arg = raw_arg.trim();
+  ensure!(!arg.is_empty(), "action `{s}` is missing an argument");
+
+  let pair = || {
+    raw_arg
+      .split_once("=>")
+      .map(|(selector, value)| (selector.trim().to_string(), value.to_string()))
+      .with_context(|| format!("action `{s}` is not of the form `{kind}:selector=>value`"))
+  };
+
+  let action = match kind.trim() {
+    "click" => Action::Click(arg.to_string()),
+    "type" => {
+      let (selector, text) = pair()?;
+      Action::Type { selector, text }
+    },
+    "hover" => Action::Hover(arg.to_string()),
+    "scroll-to" => Action::ScrollTo(arg.to_string()),
+    "press" => Action::Press(arg.to_string()),
+    "wait" => Action::Wait(parse_duration(arg)?),
+    "wait-for" => Action::WaitFor(arg.to_string()),
+    "select" => {
+      let (selector, value) = pair()?;
+      Action::Select {
+        selector,
+        value: value.trim().to_string(),
+      }
+    },
+    kind => bail!("encountered unsupported action `{kind}`"),
+  };
+  Ok(action)
+}
+
+/// Parse the contents of a steps file, containing one action (as
+/// accepted by [`parse_action`]) per line.
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub(crate) fn parse_steps(content: &str) -> Result<Vec<Action>> {
+  content
+    .lines()
+    .enumerate()
+    .filter(|(_idx, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+    .map(|(idx, line)| parse_action(line).with_context(|| format!("error in line {}", idx + 1)))
+    .collect()
+}
+
+/// Parse a geolocation specification of the form
+/// `latitude,longitude[,accuracy]`.
+fn parse_geolocation(s: &str) -> Result<Geolocation> {
+  let values = s
+    .split(',')
+    .map(|value| {
+      f64::from_str(value.trim()).with_context(|| format!("failed to parse `{value}` as number"))
+    })
+    .collect::<Result<Vec<_>>>()?;
+
+  let (latitude, longitude, accuracy) = match values.as_slice() {
+    [latitude, longitude] => (*latitude, *longitude, 1.0),
+    [latitude, longitude, accuracy] => (*latitude, *longitude, *accuracy),
+    _ => bail!("geolocation `{s}` is not of the form `latitude,longitude[,accuracy]`"),
+  };
+  ensure!(
+    (-90.0..=90.0).contains(&latitude),
+    "latitude {latitude} is out of range"
+  );
+  ensure!(
+    (-180.0..=180.0).contains(&longitude),
+    "longitude {longitude} is out of range"
+  );
+  ensure!(accuracy >= 0.0, "accuracy {accuracy} must not be negative");
+
+  Ok(Geolocation {
+    latitude,
+    longitude,
+    accuracy,
+    _non_exhaustive: (),
+  })
+}
+
+/// Parse a page region specification (`x,y,width,height`, in CSS
+/// pixels) from a string.
+fn parse_clip(s: &str) -> Result<Bounds> {
+  let values = s
+    .split(',')
+    .map(|value| {
+      f64::from_str(value.trim()).with_context(|| format!("failed to parse `{value}` as number"))
+    })
+    .collect::<Result<Vec<_>>>()?;
+
+  match values.as_slice() {
+    [x, y, width, height] => {
+      ensure!(
+        *x >= 0.0 && *y >= 0.0,
+        "clip region `{s}` starts outside of the page"
+      );
+      ensure!(*width > 0.0 && *height > 0.0, "clip region `{s}` is empty");
+      Ok(Bounds {
+        x: *x,
+        y: *y,
+        width: *width,
+        height: *height,
+        _non_exhaustive: (),
+      })
+    },
+    _ => bail!("clip region `{s}` is not of the form `x,y,width,height`"),
+  }
+}
+
+/// Parse a padding (in CSS pixels) from a string.
+fn parse_padding(s: &str) -> Result<f64> {
+  let padding = f64::from_str(s).with_context(|| format!("failed to parse `{s}` as number"))?;
+  ensure!(
+    padding.is_finite() && padding >= 0.0,
+    "padding `{s}` is not a non-negative number"
+  );
+  Ok(padding)
+}
+
+/// Parse an image region specification (`x,y,width,height`) from a
+/// string.
+fn parse_region(s: &str) -> Result<Region> {
+  let values = s
+    .split(',')
+    .map(|value| {
+      u32::from_str(value.trim()).with_context(|| format!("failed to parse `{value}` as number"))
+    })
+    .collect::<Result<Vec<_>>>()?;
+
+  match values.as_slice() {
+    [x, y, width, height] => Ok(Region {
+      x: *x,
+      y: *y,
+      width: *width,
+      height: *height,
+    }),
+    _ => bail!("region `{s}` is not of the form `x,y,width,height`"),
+  }
+}
+
+
 /// A program for shaving data from a URL.
 #[derive(Debug, Parser)]
 #[clap(version = env!("VERSION"))]
@@ -66,24 +315,128 @@ pub(crate) struct Args {
   /// Set the user agent to use.
   #[clap(long, global = true)]
   pub user_agent: Option<String>,
+  /// Abort the operation if it did not complete within the given
+  /// duration (e.g., `30s`, `500ms`, or `2m`).
+  #[clap(long, global = true, value_parser = parse_duration)]
+  pub timeout: Option<Duration>,
+  /// The proxy server to use (e.g., `http://proxy:3128` or
+  /// `socks5://localhost:1080`).
+  ///
+  /// If not present, the proxy is inferred from the `HTTPS_PROXY`,
+  /// `HTTP_PROXY`, and `NO_PROXY` environment variables. A proxy
+  /// configured there that is not supported (e.g., because it contains
+  /// credentials) is ignored with a warning.
+  #[clap(long, global = true)]
+  pub proxy: Option<Proxy>,
+  /// A comma separated list of hosts for which to bypass the proxy.
+  #[clap(long, global = true, requires = "proxy", value_delimiter = ',')]
+  pub proxy_bypass: Vec<String>,
+  /// An additional HTTP header (`Name: value`) to send with each
+  /// request.
+  ///
+  /// This option can be provided multiple times.
+  #[clap(long = "header", global = true, value_parser = parse_header)]
+  pub headers: Vec<(String, String)>,
+  /// HTTP basic authentication credentials (`user:password`) to answer
+  /// authentication challenges with.
+  #[clap(long, global = true, value_parser = parse_basic_auth)]
+  pub basic_auth: Option<(String, String)>,
+  /// Block requests of a resource type (`image`, `media`, `font`,
+  /// `script`, or `xhr`), to URLs matching a wildcard pattern prefixed
+  /// with `url:`, in which `*` matches any sequence of characters
+  /// (e.g., `url:*://*.example.com/*`), or to URLs matching a regular
+  /// expression prefixed with `regex:` (e.g., `regex:\.(gif|png)$`).
+  ///
+  /// This option can be provided multiple times.
+  #[clap(long = "block", global = true)]
+  pub blocks: Vec<Block>,
+  /// Block requests to a bundled list of common ad and tracker hosts.
+  #[clap(long, global = true)]
+  pub block_ads: bool,
+  /// The locale to use (e.g., `de-DE`), governing the browser's
+  /// language, the `Accept-Language` header, and formatting.
+  #[clap(long, global = true)]
+  pub locale: Option<String>,
+  /// The IANA time zone to use (e.g., `Europe/Berlin`).
+  #[clap(long, global = true)]
+  pub timezone: Option<String>,
+  /// The geolocation to report to pages
+  /// (`latitude,longitude[,accuracy]`).
+  #[clap(long, global = true, value_parser = parse_geolocation)]
+  pub geolocation: Option<Geolocation>,
+  /// The path to a JSON file describing how to log in via a form before
+  /// running the command.
+  ///
+  /// The file contains an object with `url`, `username_selector`,
+  /// `password_selector`, and `success_selector` strings and an
+  /// optional `submit_selector`. Credentials are read from the
+  /// `SHAVE_LOGIN_USER` and `SHAVE_LOGIN_PASSWORD` environment
+  /// variables, unless `--login-credentials` is provided.
+  #[clap(long, global = true)]
+  pub login: Option<PathBuf>,
+  /// The path to a file containing credentials (`user:password`) to log
+  /// in with.
+  #[clap(long, global = true, requires = "login")]
+  pub login_credentials: Option<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
 pub(crate) enum Command {
+  #[clap(flatten)]
+  Browser(BrowserCommand),
+  /// Compare two screenshots visually.
+  ///
+  /// The exit status is 0 if the screenshots match, 1 if they differ,
+  /// and 2 if an error occurred.
+  Diff(Diff),
+}
+
+/// Commands requiring a browser.
+#[derive(Debug, Subcommand)]
+pub(crate) enum BrowserCommand {
   /// Capture a screenshot of the rendered page (or part of it).
-  Screenshot(Screenshot),
+  Screenshot(Box<Screenshot>),
   /// Launch the browser in non-headless mode and wait for user input
   /// before shutting it down again.
   ///
   /// This command is mostly meant for debugging purposes.
   Launch(Launch),
+  /// Capture a suite of pages and compare them against baselines.
+  ///
+  /// The suite is a JSON file with a `cases` array, each entry of which
+  /// has a `name` (which doubles as the file name of the baseline) and
+  /// a `url` (or local file path). Cases may optionally specify
+  /// `window_size` (`[w, h]`), `await_selector`, `remove_selector`,
+  /// `mask` (`[selector, ...]`), `selector`, `stable`, `threshold`,
+  /// `max_mismatch`, and `ignore` (`[[x, y, w, h], ...]`). Top-level
+  /// attributes act as defaults for all cases.
+  ///
+  /// The exit status is 0 if all cases passed, 1 if any failed, and 2
+  /// if an error occurred.
+  Regress(Regress),
 }
 
 /// A type representing the `screenshot` command.
 #[derive(Debug, Arguments)]
 pub(crate) struct Screenshot {
-  /// The URL to navigate to.
+  /// The URL or local file path to navigate to, or `-` to read an HTML
+  /// document from standard input.
+  ///
+  /// When used with `--serve`, this argument may also be an absolute
+  /// path within the served directory.
   pub url: String,
+  /// The URL against which to resolve relative URLs in an HTML document
+  /// read from standard input.
+  ///
+  /// If not present, relative URLs are resolved against the current
+  /// working directory.
+  #[clap(long)]
+  pub base_url: Option<String>,
+  /// Serve the provided directory over HTTP on a local port, allowing
+  /// URLs that are just absolute paths (e.g., `/index.html`) to refer
+  /// to files within it.
+  #[clap(long)]
+  pub serve: Option<PathBuf>,
   /// The dimensions (W x H) of the window to configure, in pixels.
   #[clap(short, long, value_parser = parse_window_size)]
   pub window_size: Option<(usize, usize)>,
@@ -95,21 +448,183 @@ pub(crate) struct Screenshot {
   /// screenshot is captured.
   #[clap(short, long)]
   pub remove_selector: Option<String>,
+  /// A selector identifying elements to paint over with solid boxes
+  /// before the screenshot is captured, without changing the layout.
+  #[clap(long = "mask")]
+  pub mask_selectors: Vec<String>,
+  /// The CSS color to paint masked elements with.
+  #[clap(long, requires = "mask_selectors", default_value = "#ff00ff")]
+  pub mask_color: String,
   /// The selector describing the element to screenshot.
   #[clap(short, long)]
   pub selector: Option<String>,
+  /// The space to include around the element described by `--selector`,
+  /// in CSS pixels.
+  #[clap(long, requires = "selector", default_value_t = 0.0, value_parser = parse_padding)]
+  pub padding: f64,
+  /// Capture every element matching `--selector`, not just the first.
+  ///
+  /// Screenshots are written to numbered files, with `{index}` in the
+  /// output path being replaced by the (1-based) index of the match.
+  /// Metadata is written as an array.
+  #[clap(long, requires = "selector")]
+  pub all: bool,
+  /// The region of the page to screenshot (`x,y,width,height`, in CSS
+  /// pixels relative to the top left corner of the page).
+  #[clap(long, conflicts_with = "selector", value_parser = parse_clip)]
+  pub clip: Option<Bounds>,
+  /// Render the page deterministically (disabling animations, freezing
+  /// time and randomness, pausing videos, and awaiting fonts), so that
+  /// repeated captures of an unchanged page are identical.
+  #[clap(long)]
+  pub stable: bool,
+  /// The preferred color scheme to emulate (`light` or `dark`).
+  #[clap(long)]
+  pub color_scheme: Option<ColorScheme>,
+  /// The media type to emulate (`screen` or `print`).
+  #[clap(long)]
+  pub media: Option<MediaType>,
+  /// Emulate a preference for reduced motion.
+  #[clap(long)]
+  pub reduced_motion: bool,
+  /// Emulate forced colors (as used by high contrast modes).
+  #[clap(long)]
+  pub forced_colors: bool,
+  /// A style sheet to inject once the page has loaded, either as a path
+  /// to a CSS file or as CSS text.
+  #[clap(long)]
+  pub css: Vec<String>,
+  /// An interaction to perform once the page has loaded, of the form
+  /// `kind:argument`.
+  ///
+  /// Supported are `click:SELECTOR`, `type:SELECTOR=>TEXT`,
+  /// `hover:SELECTOR`, `scroll-to:SELECTOR`, `press:KEY` (e.g.,
+  /// `Enter`), `wait:DURATION`, `wait-for:SELECTOR`, and
+  /// `select:SELECTOR=>VALUE`. Whitespace surrounding arguments is
+  /// ignored, except for the text to type, which is taken verbatim.
+  /// This option can be provided multiple times, with actions being
+  /// performed in order.
+  #[clap(long = "action", value_parser = parse_action)]
+  pub actions: Vec<Action>,
+  /// The path to a file containing actions (as accepted by `--action`)
+  /// to perform, one per line.
+  ///
+  /// Actions from this file are performed before those provided via
+  /// `--action`.
+  #[clap(long)]
+  pub steps: Option<PathBuf>,
+  /// Scroll through the page before the capture, in order to trigger
+  /// lazy loading of images and other content.
+  #[clap(long)]
+  pub scroll: bool,
+  /// The distance to scroll by in each increment, in CSS pixels
+  /// (defaults to the viewport height).
+  #[clap(long, requires = "scroll")]
+  pub scroll_step: Option<usize>,
+  /// The time to wait after each scroll increment (defaults to
+  /// `100ms`).
+  #[clap(long, requires = "scroll", value_parser = parse_duration)]
+  pub scroll_delay: Option<Duration>,
+  /// The maximum height to scroll to, in CSS pixels (defaults to
+  /// 20000).
+  #[clap(long, requires = "scroll")]
+  pub scroll_max_height: Option<usize>,
+  /// The path to a JavaScript file to evaluate once the page has loaded.
+  ///
+  /// The results of evaluation are included in the metadata written
+  /// with `--metadata`.
+  #[clap(long = "script")]
+  pub scripts: Vec<PathBuf>,
+  /// The path to a JavaScript file to evaluate before any of the page's
+  /// scripts run.
+  #[clap(long = "init-script")]
+  pub init_scripts: Vec<PathBuf>,
+  /// Print messages logged to the browser console (including uncaught
+  /// JavaScript errors) to standard error.
+  #[clap(long)]
+  pub console: bool,
+  /// Fail if the page reported an uncaught JavaScript error.
+  #[clap(long)]
+  pub fail_on_js_error: bool,
+  /// A comma separated list of HTTP status codes or ranges thereof
+  /// (e.g., `200-299,404`) to accept for the main document, failing for
+  /// any other.
+  #[clap(long, value_delimiter = ',', value_parser = parse_status_range)]
+  pub accept_status: Vec<RangeInclusive<u16>>,
+  /// Write metadata about the capture (final URL, HTTP status, page
+  /// title, and more) as JSON to the provided file.
+  #[clap(long)]
+  pub metadata: Option<PathBuf>,
   /// The path to the file to write the screenshot to.
   ///
   /// If not present, write to `./<screenshot-{date}.png>` in the
-  /// current directory. Set to `-` to print data to standard output.
+  /// current directory (or `./<screenshot-{date}-{index}.png>` with
+  /// `--all`). Set to `-` to print data to standard output.
   #[clap(short, long)]
   pub output: Option<Output>,
+  /// Write a log of all network requests issued while navigating to
+  /// the given path, in HAR format.
+  #[clap(long)]
+  pub har: Option<PathBuf>,
 }
 
 /// A type representing the `launch` command.
 #[derive(Debug, Arguments)]
 pub(crate) struct Launch {}
 
+/// A type representing the `regress` command.
+#[derive(Debug, Arguments)]
+pub(crate) struct Regress {
+  /// The path to the JSON file describing the suite.
+  pub suite: PathBuf,
+  /// The directory containing baseline images.
+  #[clap(short, long, default_value = "baselines")]
+  pub baselines: PathBuf,
+  /// The directory to write actual and diff images of failing cases
+  /// to.
+  ///
+  /// If not present, images are written to a `diff` sub-directory of
+  /// the baseline directory.
+  #[clap(long)]
+  pub diff_dir: Option<PathBuf>,
+  /// Accept the captures as new baselines instead of comparing them.
+  #[clap(short, long)]
+  pub update: bool,
+  /// Write a JUnit XML report to the provided file.
+  #[clap(long)]
+  pub junit: Option<PathBuf>,
+  /// Write an HTML report to the provided file.
+  #[clap(long)]
+  pub html: Option<PathBuf>,
+}
+
+/// A type representing the `diff` command.
+#[derive(Debug, Arguments)]
+pub(crate) struct Diff {
+  /// The first PNG image to compare.
+  pub first: PathBuf,
+  /// The second PNG image to compare.
+  pub second: PathBuf,
+  /// The path to the file to write an image highlighting differences
+  /// to.
+  #[clap(short, long)]
+  pub out: Option<PathBuf>,
+  /// The per-pixel color difference to tolerate, ranging from 0 (exact
+  /// match) to 1.
+  #[clap(short, long, default_value_t = 0.1)]
+  pub threshold: f64,
+  /// Count pixels differing only due to anti-aliasing as mismatches.
+  #[clap(long)]
+  pub include_anti_aliasing: bool,
+  /// A region (`x,y,width,height`) to exclude from the comparison.
+  #[clap(long = "ignore", value_parser = parse_region)]
+  pub ignore_regions: Vec<Region>,
+  /// The percentage of differing pixels to tolerate before considering
+  /// the images different.
+  #[clap(long, default_value_t = 0.0)]
+  pub max_mismatch: f64,
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -124,6 +639,201 @@ mod tests {
     assert_eq!(parse_window_size("3840 2160").unwrap(), (3840, 2160));
   }
 
+  /// Check that we can parse a duration specification.
+  #[test]
+  fn duration_parsing() {
+    assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+    assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+    assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+    assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+    assert!(parse_duration("2h").is_err());
+    assert!(parse_duration("s").is_err());
+    assert!(parse_duration("999999999999999999m").is_err());
+  }
+
+  /// Check that we can parse HTTP header specifications.
+  #[test]
+  fn header_parsing() {
+    assert_eq!(
+      parse_header("Accept-Language: de-DE").unwrap(),
+      ("Accept-Language".to_string(), "de-DE".to_string())
+    );
+    assert_eq!(
+      parse_header("X-Empty:").unwrap(),
+      ("X-Empty".to_string(), String::new())
+    );
+    assert!(parse_header("Accept-Language").is_err());
+    assert!(parse_header(": value").is_err());
+  }
+
+  /// Check that we can parse HTTP basic authentication credentials.
+  #[test]
+  fn basic_auth_parsing() {
+    assert_eq!(
+      parse_basic_auth("user:pass:word").unwrap(),
+      ("user".to_string(), "pass:word".to_string())
+    );
+    assert!(parse_basic_auth("user").is_err());
+  }
+
+  /// Check that we can parse HTTP status codes and ranges.
+  #[test]
+  fn status_range_parsing() {
+    assert_eq!(parse_status_range("404").unwrap(), 404..=404);
+    assert_eq!(parse_status_range("200-299").unwrap(), 200..=299);
+    assert!(parse_status_range("299-200").is_err());
+    assert!(parse_status_range("2xx").is_err());
+  }
+
+  /// Check that we can parse interactions.
+  #[test]
+  fn action_parsing() {
+    assert_eq!(
+      parse_action("click:#accept").unwrap(),
+      Action::Click("#accept".to_string())
+    );
+    assert_eq!(
+      parse_action("type:input[name=q]=>red shoes").unwrap(),
+      Action::Type {
+        selector: "input[name=q]".to_string(),
+        text: "red shoes".to_string(),
+      }
+    );
+    assert_eq!(
+      parse_action("select: #country => DE").unwrap(),
+      Action::Select {
+        selector: "#country".to_string(),
+        value: "DE".to_string(),
+      }
+    );
+    assert_eq!(
+      parse_action("type: #search => red shoes ").unwrap(),
+      Action::Type {
+        selector: "#search".to_string(),
+        text: " red shoes ".to_string(),
+      }
+    );
+    assert_eq!(
+      parse_action("wait:500ms").unwrap(),
+      Action::Wait(Duration::from_millis(500))
+    );
+    assert_eq!(
+      parse_action("hover:nav li:first-child").unwrap(),
+      Action::Hover("nav li:first-child".to_string())
+    );
+    assert!(parse_action("click").is_err());
+    assert!(parse_action("click:").is_err());
+    assert!(parse_action("type:#search").is_err());
+    assert!(parse_action("drag:#item").is_err());
+  }
+
+  /// Make sure that we can parse steps files.
+  #[test]
+  fn steps_parsing() {
+    let content = r#"
+# Dismiss the cookie banner.
+click:#accept
+
+press:Escape
+"#;
+    let steps = parse_steps(content).unwrap();
+    assert_eq!(
+      steps,
+      vec![
+        Action::Click("#accept".to_string()),
+        Action::Press("Escape".to_string())
+      ]
+    );
+
+    let err = parse_steps("click:#a\nfoo").unwrap_err();
+    assert_eq!(err.to_string(), "error in line 2");
+  }
+
+  /// Check that we can parse geolocation specifications.
+  #[test]
+  fn geolocation_parsing() {
+    let geolocation = parse_geolocation("52.52, 13.405").unwrap();
+    assert_eq!(
+      geolocation,
+      Geolocation {
+        latitude: 52.52,
+        longitude: 13.405,
+        accuracy: 1.0,
+        ..Default::default()
+      }
+    );
+    let geolocation = parse_geolocation("-33.87,151.21,50").unwrap();
+    assert_eq!(geolocation.accuracy, 50.0);
+    assert!(parse_geolocation("52.52").is_err());
+    assert!(parse_geolocation("91,0").is_err());
+    assert!(parse_geolocation("0,0,-1").is_err());
+  }
+
+  /// Check that we can parse a page region specification.
+  #[test]
+  fn clip_parsing() {
+    let clip = parse_clip("0, 1200.5,800,600").unwrap();
+    assert_eq!(
+      clip,
+      Bounds {
+        x: 0.0,
+        y: 1200.5,
+        width: 800.0,
+        height: 600.0,
+        _non_exhaustive: (),
+      }
+    );
+    assert!(parse_clip("0,0,800").is_err());
+    assert!(parse_clip("-1,0,800,600").is_err());
+    assert!(parse_clip("0,0,0,600").is_err());
+  }
+
+  /// Check that we can parse a padding.
+  #[test]
+  fn padding_parsing() {
+    assert_eq!(parse_padding("0").unwrap(), 0.0);
+    assert_eq!(parse_padding("12.5").unwrap(), 12.5);
+    assert!(parse_padding("-1").is_err());
+    assert!(parse_padding("NaN").is_err());
+    assert!(parse_padding("inf").is_err());
+  }
+
+  /// Check that we can parse an image region specification.
+  #[test]
+  fn region_parsing() {
+    let region = parse_region("10, 20,300,40").unwrap();
+    assert_eq!(
+      region,
+      Region {
+        x: 10,
+        y: 20,
+        width: 300,
+        height: 40,
+      }
+    );
+    assert!(parse_region("10,20,300").is_err());
+    assert!(parse_region("10,20,-3,40").is_err());
+  }
+
+  /// Check that we can parse a [`Block`] object from a string.
+  #[test]
+  fn block_parsing() {
+    assert_eq!(
+      Block::from_str("image").unwrap(),
+      Block::ResourceType(ResourceType::Image)
+    );
+    assert_eq!(
+      Block::from_str("url:*.gif").unwrap(),
+      Block::Pattern("*.gif".to_string())
+    );
+    assert_eq!(
+      Block::from_str("regex:^https://").unwrap(),
+      Block::Regex("^https://".to_string())
+    );
+    assert!(Block::from_str("imgae").is_err());
+    assert!(Block::from_str("*.gif").is_err());
+  }
+
   /// Check that we can parse an [`Output`] object from a string.
   #[test]
   fn output_parsing() {
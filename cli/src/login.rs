@@ -0,0 +1,119 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Functionality for logging in via a form before running a command.
+
+use std::env::var;
+
+use anyhow::ensure;
+use anyhow::Context as _;
+use anyhow::Result;
+
+use serde_json::Value;
+
+use shave::Login;
+
+
+/// The environment variable containing the user name to log in with.
+const USER_VAR: &str = "SHAVE_LOGIN_USER";
+/// The environment variable containing the password to log in with.
+const PASSWORD_VAR: &str = "SHAVE_LOGIN_PASSWORD";
+
+
+/// Parse a login recipe from its JSON description.
+///
+/// A recipe is an object with `url`, `username_selector`,
+/// `password_selector`, and `success_selector` strings and an optional
+/// `submit_selector`. Credentials are left empty.
+pub(crate) fn parse_recipe(json: &str) -> Result<Login> {
+  let recipe = serde_json::from_str::<Value>(json).context("failed to parse recipe as JSON")?;
+  let string = |key| -> Result<Option<String>> {
+    recipe
+      .get(key)
+      .map(|value| {
+        value
+          .as_str()
+          .map(str::to_string)
+          .with_context(|| format!("`{key}` attribute is not a string"))
+      })
+      .transpose()
+  };
+  let required =
+    |key| -> Result<String> { string(key)?.with_context(|| format!("recipe is missing `{key}`")) };
+
+  let login = Login {
+    url: required("url")?,
+    username_selector: required("username_selector")?,
+    password_selector: required("password_selector")?,
+    submit_selector: string("submit_selector")?,
+    success_selector: required("success_selector")?,
+    ..Default::default()
+  };
+  Ok(login)
+}
+
+/// Parse credentials of the form `user:password` as contained in a
+/// credentials file.
+///
+/// A trailing line break is ignored.
+pub(crate) fn parse_credentials(content: &str) -> Result<(String, String)> {
+  let content = content
+    .strip_suffix('\n')
+    .map(|content| content.strip_suffix('\r').unwrap_or(content))
+    .unwrap_or(content);
+  let (user, password) = content
+    .split_once(':')
+    .context("credentials are not of the form `user:password`")?;
+  ensure!(!user.is_empty(), "credentials are missing a user name");
+  Ok((user.to_string(), password.to_string()))
+}
+
+/// Retrieve credentials from the `SHAVE_LOGIN_USER` and
+/// `SHAVE_LOGIN_PASSWORD` environment variables.
+pub(crate) fn credentials_from_env() -> Result<(String, String)> {
+  let user = var(USER_VAR).with_context(|| format!("failed to read `{USER_VAR}`"))?;
+  let password = var(PASSWORD_VAR).with_context(|| format!("failed to read `{PASSWORD_VAR}`"))?;
+  Ok((user, password))
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we can parse a login recipe.
+  #[test]
+  fn recipe_parsing() {
+    let json = r##"{
+      "url": "https://example.com/login",
+      "username_selector": "#user",
+      "password_selector": "#pass",
+      "success_selector": ".dashboard"
+    }"##;
+    let login = parse_recipe(json).unwrap();
+    assert_eq!(login.url, "https://example.com/login");
+    assert_eq!(login.username_selector, "#user");
+    assert_eq!(login.password_selector, "#pass");
+    assert_eq!(login.submit_selector, None);
+    assert_eq!(login.success_selector, ".dashboard");
+
+    let err = parse_recipe(r#"{"url": "https://example.com/login"}"#).unwrap_err();
+    assert_eq!(err.to_string(), "recipe is missing `username_selector`");
+  }
+
+  /// Make sure that we can parse credentials.
+  #[test]
+  fn credentials_parsing() {
+    assert_eq!(
+      parse_credentials("alice:pa:ss\n").unwrap(),
+      ("alice".to_string(), "pa:ss".to_string())
+    );
+    assert_eq!(
+      parse_credentials("alice: \r\n").unwrap(),
+      ("alice".to_string(), " ".to_string())
+    );
+    assert!(parse_credentials("alice").is_err());
+    assert!(parse_credentials(":secret").is_err());
+  }
+}
@@ -4,12 +4,28 @@
 //! A command line interface for the `shave` library.
 
 mod args;
+mod login;
+mod regress;
 
 use std::env::args_os;
+use std::env::current_dir;
 use std::ffi::OsString;
 use std::io::stdin;
+use std::io::ErrorKind;
+use std::io::Read as _;
+use std::os::raw::c_int;
+use std::os::unix::ffi::OsStrExt as _;
+use std::os::unix::ffi::OsStringExt as _;
+use std::path::Path;
 use std::path::PathBuf;
+use std::pin::pin;
+use std::process::exit;
+use std::process::ExitCode;
+use std::time::Instant;
 
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::ensure;
 use anyhow::Context as _;
 use anyhow::Error;
 use anyhow::Result;
@@ -18,55 +34,328 @@ use clap::Parser as _;
 
 use chrono::offset::Local;
 
+use serde_json::Value;
+
+use shave::AutoScroll;
+use shave::BlockRules;
 use shave::Client;
+use shave::Proxy;
 
+use tokio::fs::create_dir_all;
+use tokio::fs::read;
+use tokio::fs::read_to_string;
 use tokio::fs::write;
 use tokio::io::stdout;
 use tokio::io::AsyncWriteExt as _;
+use tokio::select;
+use tokio::signal::unix::signal;
+use tokio::signal::unix::Signal;
+use tokio::signal::unix::SignalKind;
 use tokio::task::spawn_blocking;
 
+use url::Url;
+
+use crate::args::parse_steps;
 use crate::args::Args;
+use crate::args::Block;
+use crate::args::BrowserCommand;
 use crate::args::Command;
+use crate::args::Diff;
 use crate::args::Launch;
 use crate::args::Output;
+use crate::args::Regress;
 use crate::args::Screenshot;
+use crate::login::credentials_from_env;
+use crate::login::parse_credentials;
+use crate::login::parse_recipe;
+use crate::regress::html_report;
+use crate::regress::junit_report;
+use crate::regress::parse_suite;
+use crate::regress::Case;
+use crate::regress::CaseResult;
+use crate::regress::Outcome;
+
+
+/// The placeholder in output paths to replace with the index of a
+/// match when capturing all elements matching a selector.
+const INDEX_PLACEHOLDER: &str = "{index}";
+
+
+/// Convert the provided URL or path to a local file into a URL.
+fn to_url(s: &str) -> Result<String> {
+  let path = Path::new(s);
+  if Url::parse(s).is_err() && path.exists() {
+    let path = path
+      .canonicalize()
+      .with_context(|| format!("failed to canonicalize `{}`", path.display()))?;
+    let url = Url::from_file_path(&path)
+      .map_err(|()| anyhow!("failed to convert `{}` to URL", path.display()))?;
+    Ok(url.into())
+  } else {
+    Ok(s.to_string())
+  }
+}
+
+/// Replace all occurrences of the index placeholder in the provided
+/// path with `index`.
+///
+/// `None` is returned if the path does not contain the placeholder.
+fn substitute_index(template: &Path, index: usize) -> Option<PathBuf> {
+  let template = template.as_os_str().as_bytes();
+  let placeholder = INDEX_PLACEHOLDER.as_bytes();
+  let index = index.to_string();
+  let mut path = Vec::with_capacity(template.len());
+  let mut rest = template;
+  let mut found = false;
+
+  while !rest.is_empty() {
+    if let Some(remainder) = rest.strip_prefix(placeholder) {
+      let () = path.extend_from_slice(index.as_bytes());
+      rest = remainder;
+      found = true;
+    } else {
+      let () = path.push(rest[0]);
+      rest = &rest[1..];
+    }
+  }
+  found.then(|| PathBuf::from(OsString::from_vec(path)))
+}
+
+/// Read an HTML document from standard input.
+async fn read_html() -> Result<String> {
+  spawn_blocking(|| {
+    let mut html = String::new();
+    let _count = stdin().read_to_string(&mut html)?;
+    Result::<_, Error>::Ok(html)
+  })
+  .await
+  .unwrap()
+  .context("failed to read HTML document from stdin")
+}
 
+/// Read the contents of each of the provided files.
+async fn read_files(paths: &[PathBuf]) -> Result<Vec<String>> {
+  let mut contents = Vec::with_capacity(paths.len());
+  for path in paths {
+    let content = read_to_string(path)
+      .await
+      .with_context(|| format!("failed to read `{}`", path.display()))?;
+    let () = contents.push(content);
+  }
+  Ok(contents)
+}
 
 /// Handler for the `screenshot` command.
 async fn screenshot(client: &mut Client, screenshot: Screenshot) -> Result<()> {
   let Screenshot {
     url,
+    base_url,
+    serve,
     window_size,
     await_selector,
     remove_selector,
+    mask_selectors,
+    mask_color,
     selector,
+    padding,
+    all,
+    clip,
+    stable,
+    color_scheme,
+    media,
+    reduced_motion,
+    forced_colors,
+    css,
+    actions: extra_actions,
+    steps,
+    scroll,
+    scroll_step,
+    scroll_delay,
+    scroll_max_height,
+    scripts,
+    init_scripts,
+    console,
+    fail_on_js_error,
+    accept_status,
+    metadata,
     output,
+    har,
   } = screenshot;
 
+  let mut style_sheets = Vec::with_capacity(css.len());
+  for css in css {
+    let path = Path::new(&css);
+    let css = if path.is_file() {
+      read_to_string(path)
+        .await
+        .with_context(|| format!("failed to read `{}`", path.display()))?
+    } else {
+      css
+    };
+    let () = style_sheets.push(css);
+  }
+
+  let mut actions = if let Some(steps) = steps {
+    let content = read_to_string(&steps)
+      .await
+      .with_context(|| format!("failed to read `{}`", steps.display()))?;
+    parse_steps(&content)
+      .with_context(|| format!("failed to parse steps file `{}`", steps.display()))?
+  } else {
+    Vec::new()
+  };
+  let () = actions.extend(extra_actions);
+
+  let scroll = scroll.then(|| {
+    let default = AutoScroll::default();
+    AutoScroll {
+      step: scroll_step,
+      delay: scroll_delay.unwrap_or(default.delay),
+      max_height: scroll_max_height.unwrap_or(default.max_height),
+      ..default
+    }
+  });
+
   let opts = shave::ScreenshotOpts {
     window_size,
     await_selector,
     remove_selector,
+    mask_selectors,
+    mask_color: Some(mask_color),
     selector,
+    padding,
+    clip,
+    stable,
+    media: shave::EmulatedMedia {
+      media_type: media,
+      color_scheme,
+      reduced_motion,
+      forced_colors,
+      _non_exhaustive: (),
+    },
+    css: style_sheets,
+    actions,
+    scroll,
+    scripts: read_files(&scripts).await?,
+    init_scripts: read_files(&init_scripts).await?,
+    fail_on_js_error,
+    accept_status: (!accept_status.is_empty()).then_some(accept_status),
     _non_exhaustive: (),
   };
 
-  let screenshot = client
-    .screenshot(&url, &opts)
-    .await
-    .with_context(|| format!("failed to capture screenshot of `{url}`"))?;
+  let serving = serve.is_some();
+  if let Some(serve) = serve {
+    let _url = client
+      .serve_directory(&serve)
+      .with_context(|| format!("failed to serve directory `{}`", serve.display()))?;
+  }
+
+  let result = if url == "-" {
+    let html = read_html().await?;
+    let base_url = if let Some(base_url) = base_url {
+      base_url
+    } else {
+      let dir = current_dir().context("failed to retrieve current working directory")?;
+      Url::from_directory_path(&dir)
+        .map_err(|()| anyhow!("failed to convert `{}` to URL", dir.display()))?
+        .into()
+    };
+    if all {
+      client.screenshot_html_all(&html, &base_url, &opts).await
+    } else {
+      client
+        .screenshot_html(&html, &base_url, &opts)
+        .await
+        .map(|screenshot| vec![screenshot])
+    }
+  } else {
+    let url = if serving && url.starts_with('/') {
+      url.clone()
+    } else {
+      to_url(&url)?
+    };
+    if all {
+      client.screenshot_all(&url, &opts).await
+    } else {
+      client
+        .screenshot(&url, &opts)
+        .await
+        .map(|screenshot| vec![screenshot])
+    }
+  };
+
+  // Write the network log even if the capture failed, to aid in
+  // troubleshooting.
+  if let Some(har) = har {
+    let data = format!("{:#}", client.network_log().to_har());
+    let () = write(&har, data)
+      .await
+      .with_context(|| format!("failed to write HAR data to `{}`", har.display()))?;
+  }
+
+  if console {
+    for message in client.console_log() {
+      eprintln!("{message}");
+    }
+  }
+
+  let screenshots = result.with_context(|| format!("failed to capture screenshot of `{url}`"))?;
+  ensure!(!screenshots.is_empty(), "no element matches the selector");
+  if let Some(metadata) = metadata {
+    let json = if all {
+      screenshots
+        .iter()
+        .map(shave::Screenshot::to_json)
+        .collect::<Value>()
+    } else {
+      screenshots
+        .first()
+        .map(shave::Screenshot::to_json)
+        .unwrap_or_default()
+    };
+    let data = format!("{json:#}");
+    let () = write(&metadata, data)
+      .await
+      .with_context(|| format!("failed to write metadata to `{}`", metadata.display()))?;
+  }
+
   let output = output.unwrap_or_else(|| {
     let now = Local::now();
-    let path = PathBuf::from(format!("screenshot-{}.png", now.format("%+")));
+    let suffix = if all { "-{index}" } else { "" };
+    let path = PathBuf::from(format!("screenshot-{}{suffix}.png", now.format("%+")));
     Output::Path(path)
   });
 
+  if all {
+    let template = match output {
+      Output::Path(path) => path,
+      Output::Stdout => bail!("capturing all matching elements requires an output path"),
+    };
+
+    for (idx, screenshot) in screenshots.iter().enumerate() {
+      let path = substitute_index(&template, idx + 1).with_context(|| {
+        format!(
+          "output path `{}` does not contain `{INDEX_PLACEHOLDER}`",
+          template.display()
+        )
+      })?;
+      let () = write(&path, &screenshot.image)
+        .await
+        .with_context(|| format!("failed to write screenshot data to `{}`", path.display()))?;
+    }
+    return Ok(())
+  }
+
+  let screenshot = screenshots
+    .into_iter()
+    .next()
+    .context("no screenshot was captured")?;
   match output {
-    Output::Path(path) => write(&path, &screenshot)
+    Output::Path(path) => write(&path, &screenshot.image)
       .await
       .with_context(|| format!("failed to write screenshot data to `{}`", path.display())),
     Output::Stdout => stdout()
-      .write_all(&screenshot)
+      .write_all(&screenshot.image)
       .await
       .context("failed to write screenshot data to stdout"),
   }
@@ -86,8 +375,372 @@ async fn launch(_client: &mut Client, launch: Launch) -> Result<()> {
   Ok(())
 }
 
+/// Handler for the `diff` command.
+async fn diff(diff: Diff) -> Result<ExitCode> {
+  let Diff {
+    first,
+    second,
+    out,
+    threshold,
+    include_anti_aliasing,
+    ignore_regions,
+    max_mismatch,
+  } = diff;
+
+  let png1 = read(&first)
+    .await
+    .with_context(|| format!("failed to read `{}`", first.display()))?;
+  let png2 = read(&second)
+    .await
+    .with_context(|| format!("failed to read `{}`", second.display()))?;
+
+  let opts = shave::DiffOpts {
+    threshold,
+    include_anti_aliasing,
+    ignore_regions,
+    _non_exhaustive: (),
+  };
+  let diff = shave::diff(&png1, &png2, &opts).with_context(|| {
+    format!(
+      "failed to compare `{}` and `{}`",
+      first.display(),
+      second.display()
+    )
+  })?;
+
+  if let Some(out) = out {
+    let () = write(&out, &diff.image)
+      .await
+      .with_context(|| format!("failed to write diff image to `{}`", out.display()))?;
+  }
+
+  let percentage = diff.mismatch_percentage();
+  println!(
+    "{} of {} pixels differ ({percentage:.2}%)",
+    diff.mismatched, diff.compared
+  );
+
+  if diff.is_match() || percentage <= max_mismatch {
+    Ok(ExitCode::SUCCESS)
+  } else {
+    Ok(ExitCode::from(1))
+  }
+}
+
+/// Capture a single regression test case and compare it against its
+/// baseline.
+async fn run_case(
+  client: &mut Client,
+  case: &Case,
+  baseline: &Path,
+  diff_dir: &Path,
+  update: bool,
+) -> Result<Outcome> {
+  let opts = shave::ScreenshotOpts {
+    window_size: case.window_size,
+    await_selector: case.await_selector.clone(),
+    remove_selector: case.remove_selector.clone(),
+    mask_selectors: case.mask_selectors.clone(),
+    selector: case.selector.clone(),
+    stable: case.stable,
+    ..Default::default()
+  };
+  let url = to_url(&case.url)?;
+  let screenshot = client
+    .screenshot(&url, &opts)
+    .await
+    .with_context(|| format!("failed to capture screenshot of `{}`", case.url))?;
+
+  if update {
+    let () = write(baseline, &screenshot.image)
+      .await
+      .with_context(|| format!("failed to write baseline `{}`", baseline.display()))?;
+    return Ok(Outcome::Updated)
+  }
+
+  let actual = diff_dir.join(format!("{}.actual.png", case.name));
+  let write_actual = || async {
+    write(&actual, &screenshot.image)
+      .await
+      .with_context(|| format!("failed to write `{}`", actual.display()))
+  };
+
+  let expected = match read(baseline).await {
+    Ok(expected) => expected,
+    Err(err) if err.kind() == ErrorKind::NotFound => {
+      let () = write_actual().await?;
+      return Ok(Outcome::Missing { actual })
+    },
+    Err(err) => {
+      return Err(err).with_context(|| format!("failed to read baseline `{}`", baseline.display()))
+    },
+  };
+
+  let opts = shave::DiffOpts {
+    threshold: case.threshold,
+    ignore_regions: case.ignore_regions.clone(),
+    ..Default::default()
+  };
+  let diff = shave::diff(&expected, &screenshot.image, &opts).with_context(|| {
+    format!(
+      "failed to compare against baseline `{}`",
+      baseline.display()
+    )
+  })?;
+  let mismatch = diff.mismatch_percentage();
+
+  if diff.is_match() || mismatch <= case.max_mismatch {
+    Ok(Outcome::Passed { mismatch })
+  } else {
+    let () = write_actual().await?;
+    let diff_path = diff_dir.join(format!("{}.diff.png", case.name));
+    let () = write(&diff_path, &diff.image)
+      .await
+      .with_context(|| format!("failed to write `{}`", diff_path.display()))?;
+    Ok(Outcome::Failed {
+      mismatch,
+      actual,
+      diff: diff_path,
+    })
+  }
+}
+
+/// Handler for the `regress` command.
+async fn regress(client: &mut Client, regress: Regress) -> Result<ExitCode> {
+  let Regress {
+    suite,
+    baselines,
+    diff_dir,
+    update,
+    junit,
+    html,
+  } = regress;
+
+  let json = read_to_string(&suite)
+    .await
+    .with_context(|| format!("failed to read suite `{}`", suite.display()))?;
+  let cases =
+    parse_suite(&json).with_context(|| format!("failed to parse suite `{}`", suite.display()))?;
+  let name = suite
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().into_owned())
+    .unwrap_or_default();
+
+  let diff_dir = diff_dir.unwrap_or_else(|| baselines.join("diff"));
+  for dir in [&baselines, &diff_dir] {
+    let () = create_dir_all(dir)
+      .await
+      .with_context(|| format!("failed to create directory `{}`", dir.display()))?;
+  }
+
+  let mut results = Vec::with_capacity(cases.len());
+  for case in cases {
+    let start = Instant::now();
+    let baseline = baselines.join(format!("{}.png", case.name));
+    let outcome = run_case(client, &case, &baseline, &diff_dir, update)
+      .await
+      .unwrap_or_else(|err| Outcome::Error(format!("{err:#}")));
+    println!("{}: {outcome}", case.name);
+
+    let () = results.push(CaseResult {
+      name: case.name,
+      baseline,
+      outcome,
+      duration: start.elapsed(),
+    });
+  }
+
+  if let Some(junit) = junit {
+    let () = write(&junit, junit_report(&name, &results))
+      .await
+      .with_context(|| format!("failed to write JUnit report to `{}`", junit.display()))?;
+  }
+  if let Some(html) = html {
+    let dir = html
+      .parent()
+      .filter(|dir| !dir.as_os_str().is_empty())
+      .unwrap_or_else(|| Path::new("."));
+    let () = write(&html, html_report(&name, &results, dir))
+      .await
+      .with_context(|| format!("failed to write HTML report to `{}`", html.display()))?;
+  }
+
+  if results.iter().all(|result| result.outcome.is_success()) {
+    Ok(ExitCode::SUCCESS)
+  } else {
+    Ok(ExitCode::from(1))
+  }
+}
+
+/// Run the provided command.
+async fn command(client: &mut Client, command: BrowserCommand) -> Result<ExitCode> {
+  match command {
+    BrowserCommand::Screenshot(screenshot) => self::screenshot(client, *screenshot)
+      .await
+      .map(|()| ExitCode::SUCCESS),
+    BrowserCommand::Launch(launch) => self::launch(client, launch)
+      .await
+      .map(|()| ExitCode::SUCCESS),
+    BrowserCommand::Regress(regress) => self::regress(client, regress).await,
+  }
+}
+
+/// Wait for one of the provided termination signals to arrive,
+/// returning its number.
+async fn termination(sigint: &mut Signal, sigterm: &mut Signal) -> c_int {
+  let kind = select! {
+    _ = sigint.recv() => SignalKind::interrupt(),
+    _ = sigterm.recv() => SignalKind::terminate(),
+  };
+  c_int::from(kind)
+}
+
+/// Exit the program with the conventional status for termination by
+/// the provided signal.
+fn exit_for_signal(signal: c_int) -> ! {
+  // NB: We exit the process directly, without shutting down the
+  //     runtime, as the latter would wait for any blocking tasks (such
+  //     as reading from stdin) to finish.
+  exit(128 + signal)
+}
+
+/// Run the program with the provided parsed arguments.
+async fn execute(args: Args) -> Result<ExitCode> {
+  // Comparing screenshots does not require a browser, so don't bother
+  // starting one.
+  let command = match args.command {
+    Command::Diff(diff) => return self::diff(diff).await,
+    Command::Browser(command) => command,
+  };
+
+  let login = if let Some(recipe) = &args.login {
+    let json = read_to_string(recipe)
+      .await
+      .with_context(|| format!("failed to read `{}`", recipe.display()))?;
+    let mut login = parse_recipe(&json)
+      .with_context(|| format!("failed to parse login recipe `{}`", recipe.display()))?;
+    let (username, password) = if let Some(credentials) = &args.login_credentials {
+      let content = read_to_string(credentials)
+        .await
+        .with_context(|| format!("failed to read `{}`", credentials.display()))?;
+      parse_credentials(&content)
+        .with_context(|| format!("failed to parse credentials in `{}`", credentials.display()))?
+    } else {
+      credentials_from_env()?
+    };
+    login.username = username;
+    login.password = password;
+    Some(login)
+  } else {
+    None
+  };
+
+  let mut sigint = signal(SignalKind::interrupt()).context("failed to register SIGINT handler")?;
+  let mut sigterm =
+    signal(SignalKind::terminate()).context("failed to register SIGTERM handler")?;
+
+  let mut block_rules = BlockRules {
+    ads_and_trackers: args.block_ads,
+    ..Default::default()
+  };
+  for block in args.blocks {
+    match block {
+      Block::ResourceType(resource_type) => block_rules.resource_types.push(resource_type),
+      Block::Pattern(pattern) => block_rules.patterns.push(pattern),
+      Block::Regex(regex) => block_rules.regexes.push(regex),
+    }
+  }
+
+  // Recording network activity comes with overhead, so only do so if
+  // the command makes use of it.
+  let record_network = match &command {
+    BrowserCommand::Screenshot(screenshot) => {
+      screenshot.har.is_some()
+        || screenshot.metadata.is_some()
+        || !screenshot.accept_status.is_empty()
+    },
+    _ => false,
+  };
+
+  let mut builder = shave::Client::builder()
+    .set_user_agent(args.user_agent)
+    .set_timeout(args.timeout)
+    .set_headers(args.headers)
+    .set_basic_auth(args.basic_auth)
+    .set_block_rules(block_rules)
+    .set_record_network(record_network)
+    .set_locale(args.locale)
+    .set_timezone(args.timezone)
+    .set_geolocation(args.geolocation)
+    .set_headless(!matches!(command, BrowserCommand::Launch(..)));
+
+  builder = if let Some(proxy) = args.proxy {
+    builder.set_proxy(Some(proxy.set_bypass(args.proxy_bypass)))
+  } else {
+    // A proxy configured in the environment is likely meant for other
+    // programs as well, so don't refuse to work if it is not supported.
+    match Proxy::from_env() {
+      Ok(proxy) => builder.set_proxy(proxy),
+      Err(err) => {
+        eprintln!("warning: ignoring proxy set in environment: {err:#}");
+        builder.set_proxy(None)
+      },
+    }
+  };
+
+  let mut build = pin!(builder.build());
+  let mut client = select! {
+    result = &mut build => result.context("failed to instantiate `shave` client")?,
+    signal = termination(&mut sigint, &mut sigterm) => {
+      // Abandoning the build midway would orphan the browser processes
+      // launched so far, so let it complete and clean up after it.
+      // Another signal cuts that short, though.
+      select! {
+        () = async {
+          if let Ok(client) = build.await {
+            if let Err(err) = client.destroy().await {
+              eprintln!("Error: {:?}", err.context("failed to destroy `shave` client"));
+            }
+          }
+        } => (),
+        _signal = termination(&mut sigint, &mut sigterm) => (),
+      }
+      exit_for_signal(signal)
+    },
+  };
+
+  let result = select! {
+    result = async {
+      if let Some(login) = &login {
+        let () = client.login(login).await?;
+      }
+      self::command(&mut client, command).await
+    } => result,
+    signal = termination(&mut sigint, &mut sigterm) => {
+      // The in-flight operation has been canceled at this point. All
+      // that is left to do is to clean up after ourselves, unless
+      // another signal arrives in the meantime.
+      select! {
+        result = client.destroy() => {
+          if let Err(err) = result {
+            eprintln!("Error: {:?}", err.context("failed to destroy `shave` client"));
+          }
+        },
+        _signal = termination(&mut sigint, &mut sigterm) => (),
+      }
+      exit_for_signal(signal)
+    },
+  };
+
+  let () = client
+    .destroy()
+    .await
+    .context("failed to destroy `shave` client")?;
+  result
+}
+
 /// Run the program and report errors, if any.
-async fn run<A, T>(args: A) -> Result<()>
+async fn run<A, T>(args: A) -> ExitCode
 where
   A: IntoIterator<Item = T>,
   T: Into<OsString> + Clone,
@@ -97,32 +750,57 @@ where
     Err(err) => match err.kind() {
       clap::error::ErrorKind::DisplayHelp | clap::error::ErrorKind::DisplayVersion => {
         print!("{err}");
-        return Ok(())
+        return ExitCode::SUCCESS
+      },
+      _ => {
+        eprintln!("Error: {:?}", Error::from(err));
+        return ExitCode::FAILURE
       },
-      _ => return Err(err.into()),
     },
   };
 
-  let mut client = shave::Client::builder()
-    .set_user_agent(args.user_agent)
-    .set_headless(!matches!(args.command, Command::Launch(..)))
-    .build()
-    .await
-    .context("failed to instantiate `shave` client")?;
-
-  let result = match args.command {
-    Command::Screenshot(screenshot) => self::screenshot(&mut client, screenshot).await,
-    Command::Launch(launch) => self::launch(&mut client, launch).await,
+  // Commands comparing screenshots report differences with exit status
+  // 1. Use the same status as `diff(1)` and friends for failures in
+  // their case, so that the two can be told apart.
+  let failure = match &args.command {
+    Command::Diff(..) | Command::Browser(BrowserCommand::Regress(..)) => ExitCode::from(2),
+    Command::Browser(..) => ExitCode::FAILURE,
   };
 
-  let () = client
-    .destroy()
-    .await
-    .context("failed to destroy `shave` client")?;
-  result
+  match execute(args).await {
+    Ok(code) => code,
+    Err(err) => {
+      eprintln!("Error: {err:?}");
+      failure
+    },
+  }
 }
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
   run(args_os()).await
 }
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::ffi::OsStr;
+
+
+  /// Check that we substitute the index placeholder in output paths,
+  /// including ones that are not valid UTF-8.
+  #[test]
+  fn index_substitution() {
+    assert_eq!(
+      substitute_index(Path::new("out/{index}-{index}.png"), 3),
+      Some(PathBuf::from("out/3-3.png"))
+    );
+    assert_eq!(substitute_index(Path::new("out.png"), 1), None);
+
+    let template = Path::new(OsStr::from_bytes(b"\xff-{index}.png"));
+    let expected = PathBuf::from(OsStr::from_bytes(b"\xff-12.png"));
+    assert_eq!(substitute_index(template, 12), Some(expected));
+  }
+}
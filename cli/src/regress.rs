@@ -0,0 +1,591 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Functionality for baseline based visual regression testing.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fmt::Write as _;
+use std::os::unix::ffi::OsStrExt as _;
+use std::path::absolute;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context as _;
+use anyhow::Result;
+
+use serde_json::Value;
+
+use shave::Region;
+
+
+/// A single page (or element thereof) to capture and compare.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Case {
+  /// The name of the case, doubling as the file name of its baseline.
+  pub name: String,
+  /// The URL to capture.
+  pub url: String,
+  /// The dimensions of the window to configure, in pixels.
+  pub window_size: Option<(usize, usize)>,
+  /// The selector describing an element to wait for.
+  pub await_selector: Option<String>,
+  /// The selector identifying elements to remove.
+  pub remove_selector: Option<String>,
+  /// Selectors identifying elements to mask.
+  pub mask_selectors: Vec<String>,
+  /// The selector describing the element to capture.
+  pub selector: Option<String>,
+  /// Whether to render the page deterministically.
+  pub stable: bool,
+  /// The per-pixel color difference to tolerate.
+  pub threshold: f64,
+  /// The percentage of differing pixels to tolerate.
+  pub max_mismatch: f64,
+  /// Regions to exclude from the comparison.
+  pub ignore_regions: Vec<Region>,
+}
+
+impl Case {
+  /// Create a `Case` from its JSON description, falling back to
+  /// `defaults` for unspecified optional attributes.
+  fn from_json(case: &Value, defaults: &Value) -> Result<Self> {
+    let name = case
+      .get("name")
+      .and_then(Value::as_str)
+      .context("case is missing `name` string")?;
+    ensure!(
+      !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\', '\0']),
+      "case name `{name}` is not a valid file name"
+    );
+
+    Self::from_json_with_name(name, case, defaults)
+      .with_context(|| format!("case `{name}` is invalid"))
+  }
+
+  fn from_json_with_name(name: &str, case: &Value, defaults: &Value) -> Result<Self> {
+    let get = |key| case.get(key).or_else(|| defaults.get(key));
+    let string = |key| -> Result<Option<String>> {
+      get(key)
+        .map(|value| {
+          value
+            .as_str()
+            .map(str::to_string)
+            .with_context(|| format!("`{key}` attribute is not a string"))
+        })
+        .transpose()
+    };
+    let number = |key, default| -> Result<f64> {
+      get(key)
+        .map(|value| {
+          value
+            .as_f64()
+            .with_context(|| format!("`{key}` attribute is not a number"))
+        })
+        .transpose()
+        .map(|value| value.unwrap_or(default))
+    };
+    let boolean = |key| -> Result<bool> {
+      get(key)
+        .map(|value| {
+          value
+            .as_bool()
+            .with_context(|| format!("`{key}` attribute is not a boolean"))
+        })
+        .transpose()
+        .map(Option::unwrap_or_default)
+    };
+    let numbers =
+      |value: &Value| -> Option<Vec<u64>> { value.as_array()?.iter().map(Value::as_u64).collect() };
+
+    let mask_selectors = get("mask")
+      .map(|value| {
+        value
+          .as_array()
+          .and_then(|selectors| {
+            selectors
+              .iter()
+              .map(|selector| selector.as_str().map(str::to_string))
+              .collect::<Option<Vec<_>>>()
+          })
+          .context("`mask` attribute is not an array of strings")
+      })
+      .transpose()?
+      .unwrap_or_default();
+
+    let url = case
+      .get("url")
+      .and_then(Value::as_str)
+      .context("case is missing `url` string")?;
+
+    let window_size = get("window_size")
+      .map(|value| match numbers(value).as_deref() {
+        Some([w, h]) => Ok((*w as usize, *h as usize)),
+        _ => bail!("`window_size` attribute is not of the form `[width, height]`"),
+      })
+      .transpose()?;
+
+    let ignore_regions = get("ignore")
+      .map(|value| {
+        value
+          .as_array()
+          .context("`ignore` attribute is not an array")?
+          .iter()
+          .map(|region| match numbers(region).as_deref() {
+            Some([x, y, width, height]) => Ok(Region {
+              x: *x as u32,
+              y: *y as u32,
+              width: *width as u32,
+              height: *height as u32,
+            }),
+            _ => bail!("ignored region is not of the form `[x, y, width, height]`"),
+          })
+          .collect::<Result<Vec<_>>>()
+      })
+      .transpose()?
+      .unwrap_or_default();
+
+    let slf = Self {
+      name: name.to_string(),
+      url: url.to_string(),
+      window_size,
+      await_selector: string("await_selector")?,
+      remove_selector: string("remove_selector")?,
+      mask_selectors,
+      selector: string("selector")?,
+      stable: boolean("stable")?,
+      threshold: number("threshold", 0.1)?,
+      max_mismatch: number("max_mismatch", 0.0)?,
+      ignore_regions,
+    };
+    Ok(slf)
+  }
+}
+
+
+/// Parse a test suite from its JSON description.
+///
+/// A suite is an object with a `cases` array, each entry of which
+/// describes a [`Case`]. All other attributes of the suite act as
+/// defaults for cases not specifying them.
+pub(crate) fn parse_suite(json: &str) -> Result<Vec<Case>> {
+  let suite = serde_json::from_str::<Value>(json).context("failed to parse suite as JSON")?;
+  let cases = suite
+    .get("cases")
+    .and_then(Value::as_array)
+    .context("suite is missing `cases` array")?
+    .iter()
+    .map(|case| Case::from_json(case, &suite))
+    .collect::<Result<Vec<_>>>()?;
+
+  for (idx, case) in cases.iter().enumerate() {
+    ensure!(
+      !cases[..idx].iter().any(|other| other.name == case.name),
+      "case name `{}` is not unique",
+      case.name
+    );
+  }
+  Ok(cases)
+}
+
+
+/// The outcome of running a single [`Case`].
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Outcome {
+  /// The capture matched the baseline.
+  Passed {
+    /// The percentage of differing pixels.
+    mismatch: f64,
+  },
+  /// The baseline was created or updated from the capture.
+  Updated,
+  /// The capture differed from the baseline.
+  Failed {
+    /// The percentage of differing pixels.
+    mismatch: f64,
+    /// The path to the captured image.
+    actual: PathBuf,
+    /// The path to the image highlighting differences.
+    diff: PathBuf,
+  },
+  /// No baseline exists for the case.
+  Missing {
+    /// The path to the captured image.
+    actual: PathBuf,
+  },
+  /// The case could not be run.
+  Error(String),
+}
+
+impl Outcome {
+  /// Check whether the outcome is considered successful.
+  pub fn is_success(&self) -> bool {
+    matches!(self, Self::Passed { .. } | Self::Updated)
+  }
+}
+
+impl Display for Outcome {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Self::Passed { mismatch } => write!(f, "passed ({mismatch:.2}% differ)"),
+      Self::Updated => f.write_str("baseline updated"),
+      Self::Failed { mismatch, .. } => write!(f, "FAILED ({mismatch:.2}% differ)"),
+      Self::Missing { .. } => f.write_str("FAILED (baseline missing)"),
+      Self::Error(error) => write!(f, "ERROR ({error})"),
+    }
+  }
+}
+
+
+/// The result of running a single [`Case`].
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CaseResult {
+  /// The name of the case.
+  pub name: String,
+  /// The path to the baseline image.
+  pub baseline: PathBuf,
+  /// The outcome of the case.
+  pub outcome: Outcome,
+  /// The time it took to run the case.
+  pub duration: Duration,
+}
+
+
+/// Escape a string for inclusion in XML or HTML.
+fn escape(s: &str) -> String {
+  let mut escaped = String::with_capacity(s.len());
+  for c in s.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// Create a JUnit XML report for the provided results.
+pub(crate) fn junit_report(suite: &str, results: &[CaseResult]) -> String {
+  let failures = results
+    .iter()
+    .filter(|result| {
+      matches!(
+        result.outcome,
+        Outcome::Failed { .. } | Outcome::Missing { .. }
+      )
+    })
+    .count();
+  let errors = results
+    .iter()
+    .filter(|result| matches!(result.outcome, Outcome::Error(..)))
+    .count();
+  let time = results
+    .iter()
+    .map(|result| result.duration)
+    .sum::<Duration>()
+    .as_secs_f64();
+
+  let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+  let _result = writeln!(
+    xml,
+    r#"<testsuite name="{}" tests="{}" failures="{failures}" errors="{errors}" time="{time:.3}">"#,
+    escape(suite),
+    results.len(),
+  );
+
+  for result in results {
+    let _result = write!(
+      xml,
+      r#"  <testcase name="{}" classname="{}" time="{:.3}""#,
+      escape(&result.name),
+      escape(suite),
+      result.duration.as_secs_f64()
+    );
+
+    let () = match &result.outcome {
+      Outcome::Passed { .. } | Outcome::Updated => xml.push_str("/>\n"),
+      Outcome::Failed {
+        mismatch,
+        actual,
+        diff,
+      } => {
+        let message = format!("{mismatch:.2}% of pixels differ from baseline");
+        let _result = write!(
+          xml,
+          ">\n    <failure message=\"{}\">baseline: {}\nactual: {}\ndiff: {}</failure>\n  </testcase>\n",
+          escape(&message),
+          escape(&result.baseline.display().to_string()),
+          escape(&actual.display().to_string()),
+          escape(&diff.display().to_string()),
+        );
+      },
+      Outcome::Missing { actual } => {
+        let _result = write!(
+          xml,
+          ">\n    <failure message=\"baseline is missing\">actual: {}</failure>\n  </testcase>\n",
+          escape(&actual.display().to_string()),
+        );
+      },
+      Outcome::Error(error) => {
+        let _result = write!(
+          xml,
+          ">\n    <error message=\"{}\"/>\n  </testcase>\n",
+          escape(error)
+        );
+      },
+    };
+  }
+
+  let () = xml.push_str("</testsuite>\n");
+  xml
+}
+
+/// Express `path` relative to the directory `base`, in the form of a
+/// percent-encoded URL path.
+///
+/// Both paths are made absolute first, but symbolic links are not
+/// resolved.
+fn relative_url(path: &Path, base: &Path) -> String {
+  let path = absolute(path).unwrap_or_else(|_err| path.to_path_buf());
+  let base = absolute(base).unwrap_or_else(|_err| base.to_path_buf());
+  let mut path = path.components().peekable();
+  let mut base = base.components().peekable();
+
+  while let (Some(component), Some(base_component)) = (path.peek(), base.peek()) {
+    if component != base_component {
+      break
+    }
+    let _component = path.next();
+    let _component = base.next();
+  }
+
+  let parents = base
+    .filter(|component| matches!(component, Component::Normal(..)))
+    .map(|_component| "..".to_string());
+  let children = path.map(|component| {
+    component
+      .as_os_str()
+      .as_bytes()
+      .iter()
+      .map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+          char::from(*byte).to_string()
+        },
+        _ => format!("%{byte:02X}"),
+      })
+      .collect::<String>()
+  });
+  parents.chain(children).collect::<Vec<_>>().join("/")
+}
+
+/// Create an HTML report for the provided results, to be written to a
+/// file in directory `dir`.
+///
+/// Images are referenced by their paths relative to `dir`.
+pub(crate) fn html_report(suite: &str, results: &[CaseResult], dir: &Path) -> String {
+  let img = |path: &Path| {
+    let path = escape(&relative_url(path, dir));
+    format!(r#"<a href="{path}"><img src="{path}"></a>"#)
+  };
+
+  let passed = results
+    .iter()
+    .filter(|result| result.outcome.is_success())
+    .count();
+
+  let mut html = String::new();
+  let _result = write!(
+    html,
+    r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{suite}</title>
+<style>
+  body {{ font-family: sans-serif; }}
+  table {{ border-collapse: collapse; }}
+  td, th {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }}
+  img {{ max-width: 320px; }}
+  .pass {{ color: #080; }}
+  .fail {{ color: #c00; }}
+</style>
+</head>
+<body>
+<h1>{suite}</h1>
+<p>{passed} of {} cases passed</p>
+<table>
+<tr><th>Case</th><th>Status</th><th>Baseline</th><th>Actual</th><th>Diff</th></tr>
+"#,
+    results.len(),
+    suite = escape(suite),
+  );
+
+  for result in results {
+    let name = escape(&result.name);
+    let baseline = img(&result.baseline);
+    let row = match &result.outcome {
+      Outcome::Passed { mismatch } => format!(
+        r#"<td class="pass">passed ({mismatch:.2}%)</td><td>{baseline}</td><td></td><td></td>"#
+      ),
+      Outcome::Updated => {
+        format!(r#"<td class="pass">updated</td><td>{baseline}</td><td></td><td></td>"#)
+      },
+      Outcome::Failed {
+        mismatch,
+        actual,
+        diff,
+      } => format!(
+        r#"<td class="fail">failed ({mismatch:.2}%)</td><td>{baseline}</td><td>{}</td><td>{}</td>"#,
+        img(actual),
+        img(diff)
+      ),
+      Outcome::Missing { actual } => format!(
+        r#"<td class="fail">baseline missing</td><td></td><td>{}</td><td></td>"#,
+        img(actual)
+      ),
+      Outcome::Error(error) => format!(
+        r#"<td class="fail">error: {}</td><td></td><td></td><td></td>"#,
+        escape(error)
+      ),
+    };
+    let _result = writeln!(html, "<tr><td>{name}</td>{row}</tr>");
+  }
+
+  let () = html.push_str("</table>\n</body>\n</html>\n");
+  html
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we can parse a test suite.
+  #[test]
+  fn suite_parsing() {
+    let json = r#"{
+      "window_size": [1280, 720],
+      "threshold": 0.2,
+      "stable": true,
+      "cases": [
+        {"name": "home", "url": "https://example.com/"},
+        {
+          "name": "header",
+          "url": "https://example.com/",
+          "selector": "header",
+          "window_size": [800, 600],
+          "max_mismatch": 0.5,
+          "mask": [".date", ".ad"],
+          "ignore": [[0, 0, 100, 20]]
+        }
+      ]
+    }"#;
+    let cases = parse_suite(json).unwrap();
+    assert_eq!(cases.len(), 2);
+    assert_eq!(cases[0].name, "home");
+    assert_eq!(cases[0].window_size, Some((1280, 720)));
+    assert_eq!(cases[0].threshold, 0.2);
+    assert_eq!(cases[0].max_mismatch, 0.0);
+    assert_eq!(cases[0].selector, None);
+    assert!(cases[0].stable);
+    assert_eq!(cases[1].selector.as_deref(), Some("header"));
+    assert_eq!(cases[1].window_size, Some((800, 600)));
+    assert_eq!(cases[1].mask_selectors, vec![".date", ".ad"]);
+    assert_eq!(cases[1].max_mismatch, 0.5);
+    assert_eq!(
+      cases[1].ignore_regions,
+      vec![Region {
+        x: 0,
+        y: 0,
+        width: 100,
+        height: 20,
+      }]
+    );
+
+    let json = r#"{"cases": [{"name": "a", "url": "x"}, {"name": "a", "url": "y"}]}"#;
+    assert!(parse_suite(json).is_err());
+    let json = r#"{"cases": [{"name": "../a", "url": "x"}]}"#;
+    assert!(parse_suite(json).is_err());
+    let json = r#"{"cases": [{"name": "a", "url": "x", "window_size": [1]}]}"#;
+    assert!(parse_suite(json).is_err());
+    let json = r#"{"cases": [{"name": "a"}]}"#;
+    assert!(parse_suite(json).is_err());
+  }
+
+  /// Check that we create the expected JUnit XML report.
+  #[test]
+  fn junit_reporting() {
+    let results = [
+      CaseResult {
+        name: "home".to_string(),
+        baseline: PathBuf::from("baselines/home.png"),
+        outcome: Outcome::Passed { mismatch: 0.0 },
+        duration: Duration::from_millis(1500),
+      },
+      CaseResult {
+        name: "header".to_string(),
+        baseline: PathBuf::from("baselines/header.png"),
+        outcome: Outcome::Failed {
+          mismatch: 1.25,
+          actual: PathBuf::from("diff/header.actual.png"),
+          diff: PathBuf::from("diff/header.diff.png"),
+        },
+        duration: Duration::from_millis(500),
+      },
+      CaseResult {
+        name: "footer".to_string(),
+        baseline: PathBuf::from("baselines/footer.png"),
+        outcome: Outcome::Error("failed to find `<footer>`".to_string()),
+        duration: Duration::ZERO,
+      },
+    ];
+
+    let xml = junit_report("suite", &results);
+    let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="suite" tests="3" failures="1" errors="1" time="2.000">
+  <testcase name="home" classname="suite" time="1.500"/>
+  <testcase name="header" classname="suite" time="0.500">
+    <failure message="1.25% of pixels differ from baseline">baseline: baselines/header.png
+actual: diff/header.actual.png
+diff: diff/header.diff.png</failure>
+  </testcase>
+  <testcase name="footer" classname="suite" time="0.000">
+    <error message="failed to find `&lt;footer&gt;`"/>
+  </testcase>
+</testsuite>
+"#;
+    assert_eq!(xml, expected);
+
+    let html = html_report("suite", &results, &absolute("reports").unwrap());
+    assert!(html.contains("<p>1 of 3 cases passed</p>"));
+    assert!(html.contains(r#"<img src="../diff/header.diff.png">"#));
+  }
+
+  /// Check that we reference files relative to a directory.
+  #[test]
+  fn relative_urls() {
+    let base = Path::new("/tmp/reports");
+    assert_eq!(relative_url(Path::new("/tmp/reports/a.png"), base), "a.png");
+    assert_eq!(
+      relative_url(Path::new("/tmp/diff/a b#1.png"), base),
+      "../diff/a%20b%231.png"
+    );
+    assert_eq!(
+      relative_url(Path::new("/srv/x/a.png"), Path::new("/tmp/reports/")),
+      "../../srv/x/a.png"
+    );
+    assert_eq!(
+      relative_url(Path::new("diff/a.png"), Path::new(".")),
+      "diff/a.png"
+    );
+  }
+}
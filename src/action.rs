@@ -0,0 +1,199 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Interactions with a page, such as clicking elements or typing text.
+
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Context as _;
+use anyhow::Result;
+
+use fantoccini::actions::InputSource as _;
+use fantoccini::actions::KeyAction;
+use fantoccini::actions::KeyActions;
+use fantoccini::actions::MouseActions;
+use fantoccini::actions::PointerAction;
+use fantoccini::key::Key;
+use fantoccini::Client as WebdriverClient;
+use fantoccini::Locator;
+
+use serde_json::json;
+
+use tokio::time::sleep;
+
+
+/// Script scrolling the element matching the selector `arguments[0]`
+/// into the center of the viewport.
+const SCROLL_SCRIPT: &str = r#"
+  const element = document.querySelector(arguments[0]);
+  if (element === null) {
+    throw new Error(`no element matches \`${arguments[0]}\``);
+  }
+  element.scrollIntoView({block: "center", inline: "center"});
+"#;
+
+
+/// Map a key name (e.g., `Enter`) or a single character onto the
+/// character WebDriver uses to represent the key.
+fn key_char(name: &str) -> Result<char> {
+  let mut chars = name.chars();
+  if let (Some(c), None) = (chars.next(), chars.next()) {
+    return Ok(c)
+  }
+
+  let key = match name {
+    "Enter" => Key::Enter,
+    "Tab" => Key::Tab,
+    "Escape" => Key::Escape,
+    "Backspace" => Key::Backspace,
+    "Delete" => Key::Delete,
+    "Space" => Key::Space,
+    "ArrowUp" => Key::Up,
+    "ArrowDown" => Key::Down,
+    "ArrowLeft" => Key::Left,
+    "ArrowRight" => Key::Right,
+    "PageUp" => Key::PageUp,
+    "PageDown" => Key::PageDown,
+    "Home" => Key::Home,
+    "End" => Key::End,
+    _ => bail!("encountered unsupported key `{name}`"),
+  };
+  Ok(char::from(key))
+}
+
+
+/// An interaction with a page.
+///
+/// Elements are identified by CSS selectors, with the first matching
+/// element being used.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Action {
+  /// Click an element.
+  Click(String),
+  /// Type text into an element.
+  Type {
+    /// The selector of the element to type into.
+    selector: String,
+    /// The text to type.
+    text: String,
+  },
+  /// Move the mouse over an element.
+  Hover(String),
+  /// Scroll an element into view.
+  ScrollTo(String),
+  /// Press a key, delivered to the focused element.
+  ///
+  /// Keys are identified by a single character or by name, with
+  /// supported names being `Enter`, `Tab`, `Escape`, `Backspace`,
+  /// `Delete`, `Space`, `ArrowUp`, `ArrowDown`, `ArrowLeft`,
+  /// `ArrowRight`, `PageUp`, `PageDown`, `Home`, and `End`.
+  Press(String),
+  /// Wait for the given duration.
+  Wait(Duration),
+  /// Wait for an element to appear.
+  WaitFor(String),
+  /// Select an option of a `<select>` element by its value.
+  Select {
+    /// The selector of the `<select>` element.
+    selector: String,
+    /// The value of the option to select.
+    value: String,
+  },
+}
+
+impl Action {
+  /// Perform the action using the provided WebDriver client.
+  pub(crate) async fn perform(&self, webdriver: &WebdriverClient) -> Result<()> {
+    match self {
+      Self::Click(selector) => {
+        let () = webdriver
+          .find(Locator::Css(selector))
+          .await
+          .with_context(|| format!("failed to find `{selector}`"))?
+          .click()
+          .await
+          .with_context(|| format!("failed to click `{selector}`"))?;
+      },
+      Self::Type { selector, text } => {
+        let () = webdriver
+          .find(Locator::Css(selector))
+          .await
+          .with_context(|| format!("failed to find `{selector}`"))?
+          .send_keys(text)
+          .await
+          .with_context(|| format!("failed to type into `{selector}`"))?;
+      },
+      Self::Hover(selector) => {
+        let element = webdriver
+          .find(Locator::Css(selector))
+          .await
+          .with_context(|| format!("failed to find `{selector}`"))?;
+        let mouse = MouseActions::new("mouse".to_string()).then(PointerAction::MoveToElement {
+          element,
+          duration: None,
+          x: 0.0,
+          y: 0.0,
+        });
+        let () = webdriver
+          .perform_actions(mouse)
+          .await
+          .with_context(|| format!("failed to hover over `{selector}`"))?;
+      },
+      Self::ScrollTo(selector) => {
+        let _output = webdriver
+          .execute(SCROLL_SCRIPT, vec![json!(selector)])
+          .await
+          .with_context(|| format!("failed to scroll to `{selector}`"))?;
+      },
+      Self::Press(key) => {
+        let value = key_char(key)?;
+        let keyboard = KeyActions::new("keyboard".to_string())
+          .then(KeyAction::Down { value })
+          .then(KeyAction::Up { value });
+        let () = webdriver
+          .perform_actions(keyboard)
+          .await
+          .with_context(|| format!("failed to press `{key}`"))?;
+      },
+      Self::Wait(duration) => {
+        let () = sleep(*duration).await;
+      },
+      Self::WaitFor(selector) => {
+        let _elem = webdriver
+          .wait()
+          .for_element(Locator::Css(selector))
+          .await
+          .with_context(|| format!("failed to await `{selector}`"))?;
+      },
+      Self::Select { selector, value } => {
+        let () = webdriver
+          .find(Locator::Css(selector))
+          .await
+          .with_context(|| format!("failed to find `{selector}`"))?
+          .select_by_value(value)
+          .await
+          .with_context(|| format!("failed to select `{value}` in `{selector}`"))?;
+      },
+    }
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we map key names onto the expected characters.
+  #[test]
+  fn key_mapping() {
+    assert_eq!(key_char("a").unwrap(), 'a');
+    assert_eq!(key_char("Enter").unwrap(), char::from(Key::Enter));
+    assert_eq!(key_char("ArrowDown").unwrap(), char::from(Key::Down));
+    assert!(key_char("Hyper").is_err());
+    assert!(key_char("").is_err());
+  }
+}
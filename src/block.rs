@@ -0,0 +1,442 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::str::from_utf8;
+use std::str::FromStr;
+
+use anyhow::bail;
+use anyhow::Context as _;
+use anyhow::Error;
+use anyhow::Result;
+
+use regex::Regex;
+use regex_syntax::hir::literal::ExtractKind;
+use regex_syntax::hir::literal::Extractor;
+
+use serde_json::json;
+use serde_json::Value;
+
+use url::Url;
+
+
+/// Hosts commonly serving ads or tracking scripts.
+static AD_AND_TRACKER_HOSTS: [&str; 32] = [
+  "2mdn.net",
+  "adnxs.com",
+  "adsafeprotected.com",
+  "adsrvr.org",
+  "adservice.google.com",
+  "amazon-adsystem.com",
+  "bluekai.com",
+  "casalemedia.com",
+  "chartbeat.com",
+  "connect.facebook.net",
+  "criteo.com",
+  "criteo.net",
+  "demdex.net",
+  "doubleclick.net",
+  "doubleverify.com",
+  "google-analytics.com",
+  "googleadservices.com",
+  "googlesyndication.com",
+  "googletagmanager.com",
+  "googletagservices.com",
+  "hotjar.com",
+  "krxd.net",
+  "moatads.com",
+  "openx.net",
+  "outbrain.com",
+  "pubmatic.com",
+  "quantserve.com",
+  "rubiconproject.com",
+  "scorecardresearch.com",
+  "smartadserver.com",
+  "taboola.com",
+  "yieldmo.com",
+];
+
+/// Check whether `url` matches the wildcard `pattern`, in which `*`
+/// matches any sequence of characters.
+fn glob_match(pattern: &str, url: &str) -> bool {
+  let mut parts = pattern.split('*');
+  // There always is at least one part.
+  let first = parts.next().unwrap_or_default();
+  let mut rest = match url.strip_prefix(first) {
+    Some(rest) => rest,
+    None => return false,
+  };
+
+  let mut parts = parts.collect::<Vec<_>>();
+  let last = match parts.pop() {
+    Some(last) => last,
+    // No wildcard at all.
+    None => return rest.is_empty(),
+  };
+
+  for part in parts {
+    match rest.find(part) {
+      Some(idx) => rest = &rest[idx + part.len()..],
+      None => return false,
+    }
+  }
+  rest.ends_with(last)
+}
+
+/// Escape a wildcard pattern for usage as a `Fetch` URL pattern, in
+/// which `?` is a wildcard as well.
+fn escape_pattern(pattern: &str) -> String {
+  pattern.replace('\\', "\\\\").replace('?', "\\?")
+}
+
+/// Create `Fetch` URL patterns matching at least all URLs that the
+/// provided regular expression matches.
+///
+/// Every match of the expression starts with one of its prefix
+/// literals, so it suffices to intercept requests to URLs containing
+/// one of them. If there is no (non-empty) such literal, all requests
+/// need to be intercepted.
+fn regex_patterns(regex: &str) -> Vec<String> {
+  let all = || vec!["*".to_string()];
+  let hir = match regex_syntax::parse(regex) {
+    Ok(hir) => hir,
+    Err(_) => return all(),
+  };
+  let seq = Extractor::new().kind(ExtractKind::Prefix).extract(&hir);
+  let literals = match seq.literals() {
+    Some(literals) if !literals.is_empty() => literals,
+    _ => return all(),
+  };
+
+  let mut patterns = Vec::with_capacity(literals.len());
+  for literal in literals {
+    // Literals may have been cut off in the middle of a character, in
+    // which case we make do with the remaining prefix.
+    let bytes = literal.as_bytes();
+    let literal = match from_utf8(bytes) {
+      Ok(literal) => literal,
+      Err(err) => from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default(),
+    };
+    if literal.is_empty() {
+      return all()
+    }
+    let literal = escape_pattern(literal).replace('*', "\\*");
+    let () = patterns.push(format!("*{literal}*"));
+  }
+  patterns
+}
+
+
+/// A type of resource that can be blocked.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ResourceType {
+  /// Images.
+  Image,
+  /// Audio and video.
+  Media,
+  /// Web fonts.
+  Font,
+  /// Scripts.
+  Script,
+  /// Requests issued by scripts via `XMLHttpRequest` or `fetch`.
+  Xhr,
+}
+
+impl ResourceType {
+  /// Retrieve the DevTools resource types corresponding to the
+  /// resource type.
+  fn devtools_types(&self) -> &'static [&'static str] {
+    match self {
+      Self::Image => &["Image"],
+      Self::Media => &["Media"],
+      Self::Font => &["Font"],
+      Self::Script => &["Script"],
+      Self::Xhr => &["XHR", "Fetch"],
+    }
+  }
+}
+
+impl FromStr for ResourceType {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "image" => Ok(Self::Image),
+      "media" => Ok(Self::Media),
+      "font" => Ok(Self::Font),
+      "script" => Ok(Self::Script),
+      "xhr" => Ok(Self::Xhr),
+      _ => bail!("unsupported resource type `{s}`"),
+    }
+  }
+}
+
+
+/// A set of rules describing requests to block.
+///
+/// Rules are enforced by intercepting requests before they are sent
+/// out. Blocked requests fail with `net::ERR_BLOCKED_BY_CLIENT`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BlockRules {
+  /// URL patterns of requests to block, in which `*` matches any
+  /// sequence of characters (e.g., `*://*.example.com/*` or `*.gif`).
+  pub patterns: Vec<String>,
+  /// Regular expressions matched against the URLs of requests,
+  /// blocking those that they match anywhere in (e.g.,
+  /// `^https?://ads\.` or `\.(gif|png)$`).
+  ///
+  /// The syntax is that of the [`regex`](https://docs.rs/regex) crate.
+  /// Expressions that don't start with a literal (e.g., `.*\.gif$`)
+  /// require all requests to be intercepted.
+  pub regexes: Vec<String>,
+  /// The types of resources to block.
+  pub resource_types: Vec<ResourceType>,
+  /// Whether to block requests to a bundled list of common ad and
+  /// tracker hosts.
+  pub ads_and_trackers: bool,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// The compiled form of [`BlockRules`], deciding which requests to
+/// block.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Blocker {
+  rules: BlockRules,
+  regexes: Vec<Regex>,
+}
+
+impl Blocker {
+  /// Create a `Blocker` enforcing the provided rules.
+  pub(crate) fn new(rules: BlockRules) -> Result<Self> {
+    let regexes = rules
+      .regexes
+      .iter()
+      .map(|regex| {
+        Regex::new(regex).with_context(|| format!("failed to parse regular expression `{regex}`"))
+      })
+      .collect::<Result<_>>()?;
+    Ok(Self { rules, regexes })
+  }
+
+  /// Check whether no requests are to be blocked.
+  pub(crate) fn is_empty(&self) -> bool {
+    let BlockRules {
+      patterns,
+      regexes,
+      resource_types,
+      ads_and_trackers,
+      _non_exhaustive: (),
+    } = &self.rules;
+
+    patterns.is_empty() && regexes.is_empty() && resource_types.is_empty() && !ads_and_trackers
+  }
+
+  /// Retrieve the `Fetch` request patterns for intercepting at least
+  /// all requests that may have to be blocked.
+  pub(crate) fn fetch_patterns(&self) -> Vec<Value> {
+    let BlockRules {
+      patterns,
+      regexes,
+      resource_types,
+      ads_and_trackers,
+      _non_exhaustive: (),
+    } = &self.rules;
+
+    // The browser does not understand regular expressions, so we
+    // intercept requests that may match them and check ourselves.
+    let regex_patterns = regexes.iter().flat_map(|regex| regex_patterns(regex));
+    if regex_patterns.clone().any(|pattern| pattern == "*") {
+      return vec![json!({"urlPattern": "*"})]
+    }
+
+    let mut fetch_patterns = patterns
+      .iter()
+      .map(|pattern| escape_pattern(pattern))
+      .chain(regex_patterns)
+      .map(|pattern| json!({"urlPattern": pattern}))
+      .collect::<Vec<_>>();
+
+    for resource_type in resource_types {
+      for devtools_type in resource_type.devtools_types() {
+        let () = fetch_patterns.push(json!({"urlPattern": "*", "resourceType": devtools_type}));
+      }
+    }
+
+    if *ads_and_trackers {
+      for host in AD_AND_TRACKER_HOSTS {
+        let () = fetch_patterns.push(json!({"urlPattern": format!("*://{host}/*")}));
+        let () = fetch_patterns.push(json!({"urlPattern": format!("*://*.{host}/*")}));
+      }
+    }
+    fetch_patterns
+  }
+
+  /// Check whether a request to `url` of the given DevTools resource
+  /// type is to be blocked.
+  pub(crate) fn is_blocked(&self, url: &str, resource_type: &str) -> bool {
+    let BlockRules {
+      patterns,
+      regexes: _,
+      resource_types,
+      ads_and_trackers,
+      _non_exhaustive: (),
+    } = &self.rules;
+
+    let blocked_type = || {
+      resource_types
+        .iter()
+        .any(|type_| type_.devtools_types().contains(&resource_type))
+    };
+    let blocked_host = || {
+      let url = Url::parse(url).ok();
+      let host = url.as_ref().and_then(Url::host_str).unwrap_or_default();
+      AD_AND_TRACKER_HOSTS.iter().any(|blocked| {
+        host == *blocked
+          || host
+            .strip_suffix(blocked)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+      })
+    };
+
+    patterns.iter().any(|pattern| glob_match(pattern, url))
+      || self.regexes.iter().any(|regex| regex.is_match(url))
+      || blocked_type()
+      || (*ads_and_trackers && blocked_host())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we can parse resource types.
+  #[test]
+  fn resource_type_parsing() {
+    assert_eq!(
+      ResourceType::from_str("image").unwrap(),
+      ResourceType::Image
+    );
+    assert_eq!(ResourceType::from_str("xhr").unwrap(), ResourceType::Xhr);
+    assert!(ResourceType::from_str("stylesheet").is_err());
+  }
+
+  /// Check that we match wildcard patterns as expected.
+  #[test]
+  fn glob_matching() {
+    assert!(glob_match("*.gif", "https://example.com/a.gif"));
+    assert!(!glob_match("*.gif", "https://example.com/a.gif?x=1"));
+    assert!(glob_match(
+      "*://*.example.com/*",
+      "https://cdn.example.com/x.js"
+    ));
+    assert!(!glob_match(
+      "*://*.example.com/*",
+      "https://example.com/x.js"
+    ));
+    assert!(glob_match("https://example.com/", "https://example.com/"));
+    assert!(!glob_match("https://example.com/", "https://example.com/x"));
+    assert!(glob_match("*a*a*", "aa"));
+    assert!(!glob_match("*a*a*", "a"));
+    assert_eq!(escape_pattern("*/a?b\\c"), "*/a\\?b\\\\c");
+  }
+
+  /// Check that we generate the expected `Fetch` patterns for blocking
+  /// rules.
+  #[test]
+  fn fetch_patterns() {
+    let blocker = Blocker::new(BlockRules::default()).unwrap();
+    assert!(blocker.is_empty());
+    assert_eq!(blocker.fetch_patterns(), Vec::<Value>::new());
+
+    let rules = BlockRules {
+      patterns: vec!["*://cdn.example.com/*".to_string()],
+      resource_types: vec![ResourceType::Font, ResourceType::Xhr],
+      ..Default::default()
+    };
+    let blocker = Blocker::new(rules).unwrap();
+    assert_eq!(
+      blocker.fetch_patterns(),
+      vec![
+        json!({"urlPattern": "*://cdn.example.com/*"}),
+        json!({"urlPattern": "*", "resourceType": "Font"}),
+        json!({"urlPattern": "*", "resourceType": "XHR"}),
+        json!({"urlPattern": "*", "resourceType": "Fetch"}),
+      ]
+    );
+
+    let rules = BlockRules {
+      regexes: vec![
+        r"^https?://ads\.".to_string(),
+        r"cdn\*\?\.(net|org)/".to_string(),
+      ],
+      resource_types: vec![ResourceType::Image],
+      ..Default::default()
+    };
+    let blocker = Blocker::new(rules).unwrap();
+    assert_eq!(
+      blocker.fetch_patterns(),
+      vec![
+        json!({"urlPattern": "*https://ads.*"}),
+        json!({"urlPattern": "*http://ads.*"}),
+        json!({"urlPattern": "*cdn\\*\\?.net/*"}),
+        json!({"urlPattern": "*cdn\\*\\?.org/*"}),
+        json!({"urlPattern": "*", "resourceType": "Image"}),
+      ]
+    );
+
+    let rules = BlockRules {
+      regexes: vec![r".*\.gif$".to_string()],
+      ads_and_trackers: true,
+      ..Default::default()
+    };
+    let blocker = Blocker::new(rules).unwrap();
+    assert_eq!(blocker.fetch_patterns(), vec![json!({"urlPattern": "*"})]);
+
+    let rules = BlockRules {
+      regexes: vec!["(".to_string()],
+      ..Default::default()
+    };
+    assert!(Blocker::new(rules).is_err());
+  }
+
+  /// Check that we decide correctly which requests to block.
+  #[test]
+  fn request_blocking() {
+    let rules = BlockRules {
+      patterns: vec!["*.woff2".to_string()],
+      regexes: vec![r"^https://static\.example\.com/.*\.(png|jpe?g)$".to_string()],
+      resource_types: vec![ResourceType::Xhr],
+      ads_and_trackers: true,
+      ..Default::default()
+    };
+    let blocker = Blocker::new(rules).unwrap();
+    assert!(blocker.is_blocked("https://example.com/font.woff2", "Font"));
+    assert!(blocker.is_blocked("https://static.example.com/a/b.jpg", "Image"));
+    assert!(!blocker.is_blocked("https://static.example.com/a/b.gif", "Image"));
+    assert!(blocker.is_blocked("https://example.com/api", "Fetch"));
+    assert!(blocker.is_blocked("https://example.com/api", "XHR"));
+    assert!(!blocker.is_blocked("https://example.com/", "Document"));
+    assert!(blocker.is_blocked("https://doubleclick.net/x", "Script"));
+    assert!(blocker.is_blocked("https://ad.doubleclick.net/x", "Script"));
+    assert!(!blocker.is_blocked("https://notdoubleclick.net/x", "Script"));
+    assert!(!blocker.is_blocked("https://example.com/?doubleclick.net/", "Script"));
+  }
+
+  /// Make sure that matching long URLs against regular expressions
+  /// prone to backtracking completes quickly.
+  #[test]
+  fn regex_blocking_long_url() {
+    let rules = BlockRules {
+      regexes: vec!["^(a+)+$".to_string()],
+      ..Default::default()
+    };
+    let blocker = Blocker::new(rules).unwrap();
+    let url = format!("https://example.com/{}b", "a".repeat(100_000));
+    assert!(!blocker.is_blocked(&url, "Document"));
+  }
+}
@@ -0,0 +1,588 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Typed wrappers around commonly used Chrome DevTools Protocol
+//! methods.
+
+use std::str::FromStr;
+
+use anyhow::bail;
+use anyhow::Context as _;
+use anyhow::Error;
+use anyhow::Result;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::Bounds;
+use crate::Client;
+
+
+/// Device metrics to emulate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceMetrics {
+  /// The width of the viewport, in CSS pixels.
+  pub width: usize,
+  /// The height of the viewport, in CSS pixels.
+  pub height: usize,
+  /// The device scale factor (device pixels per CSS pixel).
+  pub device_scale_factor: f64,
+  /// Whether to emulate a mobile device.
+  pub mobile: bool,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl DeviceMetrics {
+  fn to_params(&self) -> Value {
+    let Self {
+      width,
+      height,
+      device_scale_factor,
+      mobile,
+      _non_exhaustive: (),
+    } = self;
+
+    json!({
+      "width": width,
+      "height": height,
+      "deviceScaleFactor": device_scale_factor,
+      "mobile": mobile,
+    })
+  }
+}
+
+impl Default for DeviceMetrics {
+  fn default() -> Self {
+    Self {
+      width: 1920,
+      height: 1080,
+      device_scale_factor: 1.0,
+      mobile: false,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// A geolocation to emulate.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Geolocation {
+  /// The latitude, in degrees.
+  pub latitude: f64,
+  /// The longitude, in degrees.
+  pub longitude: f64,
+  /// The accuracy of the location, in meters.
+  pub accuracy: f64,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Geolocation {
+  fn to_params(&self) -> Value {
+    let Self {
+      latitude,
+      longitude,
+      accuracy,
+      _non_exhaustive: (),
+    } = self;
+
+    json!({
+      "latitude": latitude,
+      "longitude": longitude,
+      "accuracy": accuracy,
+    })
+  }
+}
+
+
+/// A media type to emulate.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MediaType {
+  /// The `screen` media type.
+  Screen,
+  /// The `print` media type.
+  Print,
+}
+
+impl MediaType {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Self::Screen => "screen",
+      Self::Print => "print",
+    }
+  }
+}
+
+impl FromStr for MediaType {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "screen" => Ok(Self::Screen),
+      "print" => Ok(Self::Print),
+      _ => bail!("unsupported media type `{s}`"),
+    }
+  }
+}
+
+
+/// A preferred color scheme to emulate.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColorScheme {
+  /// A light color scheme.
+  Light,
+  /// A dark color scheme.
+  Dark,
+}
+
+impl ColorScheme {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Self::Light => "light",
+      Self::Dark => "dark",
+    }
+  }
+}
+
+impl FromStr for ColorScheme {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "light" => Ok(Self::Light),
+      "dark" => Ok(Self::Dark),
+      _ => bail!("unsupported color scheme `{s}`"),
+    }
+  }
+}
+
+
+/// Media type and features to emulate.
+///
+/// Unset attributes are not emulated, i.e., the browser's defaults
+/// apply.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EmulatedMedia {
+  /// The media type to emulate.
+  pub media_type: Option<MediaType>,
+  /// The `prefers-color-scheme` media feature to emulate.
+  pub color_scheme: Option<ColorScheme>,
+  /// Whether to emulate `prefers-reduced-motion: reduce`.
+  pub reduced_motion: bool,
+  /// Whether to emulate `forced-colors: active`.
+  pub forced_colors: bool,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl EmulatedMedia {
+  fn to_params(&self) -> Value {
+    let Self {
+      media_type,
+      color_scheme,
+      reduced_motion,
+      forced_colors,
+      _non_exhaustive: (),
+    } = self;
+
+    let mut features = Vec::new();
+    if let Some(color_scheme) = color_scheme {
+      let () = features.push(json!({
+        "name": "prefers-color-scheme",
+        "value": color_scheme.as_str(),
+      }));
+    }
+    if *reduced_motion {
+      let () = features.push(json!({"name": "prefers-reduced-motion", "value": "reduce"}));
+    }
+    if *forced_colors {
+      let () = features.push(json!({"name": "forced-colors", "value": "active"}));
+    }
+
+    json!({
+      "media": media_type.as_ref().map(MediaType::as_str).unwrap_or_default(),
+      "features": features,
+    })
+  }
+}
+
+
+/// A type encompassing options for printing a page to PDF.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PdfOpts {
+  /// Whether to use landscape orientation.
+  pub landscape: bool,
+  /// Whether to print background graphics.
+  pub print_background: bool,
+  /// The scale of the page rendering (defaults to `1`).
+  pub scale: Option<f64>,
+  /// The paper width and height, in inches (defaults to US Letter).
+  pub paper_size: Option<(f64, f64)>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl PdfOpts {
+  fn to_params(&self) -> Value {
+    let Self {
+      landscape,
+      print_background,
+      scale,
+      paper_size,
+      _non_exhaustive: (),
+    } = self;
+
+    let mut params = Map::new();
+    let _prev = params.insert("landscape".to_string(), json!(landscape));
+    let _prev = params.insert("printBackground".to_string(), json!(print_background));
+    if let Some(scale) = scale {
+      let _prev = params.insert("scale".to_string(), json!(scale));
+    }
+    if let Some((w, h)) = paper_size {
+      let _prev = params.insert("paperWidth".to_string(), json!(w));
+      let _prev = params.insert("paperHeight".to_string(), json!(h));
+    }
+    Value::Object(params)
+  }
+}
+
+
+/// Create the parameters for capturing a PNG screenshot of the
+/// provided region of the page.
+fn capture_params(region: &Bounds) -> Value {
+  let Bounds {
+    x,
+    y,
+    width,
+    height,
+    _non_exhaustive: (),
+  } = region;
+
+  json!({
+    "format": "png",
+    "captureBeyondViewport": true,
+    "clip": {"x": x, "y": y, "width": width, "height": height, "scale": 1},
+  })
+}
+
+/// Decode base64 encoded binary data as contained in a DevTools
+/// response.
+pub(crate) fn decode_data(value: &Value) -> Result<Vec<u8>> {
+  let data = value
+    .get("data")
+    .and_then(Value::as_str)
+    .context("DevTools response does not contain `data` string")?;
+  let data = BASE64
+    .decode(data)
+    .context("failed to base64 decode DevTools response data")?;
+  Ok(data)
+}
+
+
+/// Typed wrappers around commonly used DevTools methods.
+///
+/// Please note that only methods can be invoked, but events can't be
+/// received.
+impl Client {
+  /// Override the device metrics (`Emulation.setDeviceMetricsOverride`)
+  /// or clear a previously set override, if `None` is provided.
+  pub async fn set_device_metrics(&self, metrics: Option<&DeviceMetrics>) -> Result<()> {
+    let _value = if let Some(metrics) = metrics {
+      self
+        .cdp("Emulation.setDeviceMetricsOverride", metrics.to_params())
+        .await?
+    } else {
+      self
+        .cdp("Emulation.clearDeviceMetricsOverride", json!({}))
+        .await?
+    };
+    Ok(())
+  }
+
+  /// Emulate the provided media type and features
+  /// (`Emulation.setEmulatedMedia`), replacing a previous emulation.
+  ///
+  /// Pass [`EmulatedMedia::default()`] to stop emulation.
+  pub async fn set_emulated_media(&self, media: &EmulatedMedia) -> Result<()> {
+    let _value = self
+      .cdp("Emulation.setEmulatedMedia", media.to_params())
+      .await?;
+    Ok(())
+  }
+
+  /// Override the locale used for formatting via `Intl` APIs
+  /// (`Emulation.setLocaleOverride`) or clear a previously set
+  /// override, if `None` is provided.
+  pub async fn set_locale(&self, locale: Option<&str>) -> Result<()> {
+    let params = match locale {
+      Some(locale) => json!({"locale": locale}),
+      None => json!({}),
+    };
+    let _value = self.cdp("Emulation.setLocaleOverride", params).await?;
+    Ok(())
+  }
+
+  /// Override the IANA time zone (`Emulation.setTimezoneOverride`) or
+  /// clear a previously set override, if `None` is provided.
+  pub async fn set_timezone(&self, timezone: Option<&str>) -> Result<()> {
+    let params = json!({"timezoneId": timezone.unwrap_or_default()});
+    let _value = self
+      .cdp("Emulation.setTimezoneOverride", params)
+      .await
+      .with_context(|| format!("failed to set time zone `{}`", timezone.unwrap_or_default()))?;
+    Ok(())
+  }
+
+  /// Override the geolocation (`Emulation.setGeolocationOverride`) or
+  /// clear a previously set override, if `None` is provided.
+  ///
+  /// When setting an override, all pages are granted permission to
+  /// query the geolocation (`Browser.grantPermissions`).
+  pub async fn set_geolocation(&self, geolocation: Option<&Geolocation>) -> Result<()> {
+    let params = if let Some(geolocation) = geolocation {
+      let _value = self
+        .cdp(
+          "Browser.grantPermissions",
+          json!({"permissions": ["geolocation"]}),
+        )
+        .await?;
+      geolocation.to_params()
+    } else {
+      json!({})
+    };
+
+    let _value = self.cdp("Emulation.setGeolocationOverride", params).await?;
+    Ok(())
+  }
+
+  /// Set additional HTTP headers to send with each request
+  /// (`Network.setExtraHTTPHeaders`), replacing previously set ones.
+  pub async fn set_extra_headers(&self, headers: &[(String, String)]) -> Result<()> {
+    let headers = headers
+      .iter()
+      .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+      .collect::<Map<_, _>>();
+
+    let _value = self.cdp("Network.enable", json!({})).await?;
+    let _value = self
+      .cdp("Network.setExtraHTTPHeaders", json!({"headers": headers}))
+      .await?;
+    Ok(())
+  }
+
+  /// Clear all browser cookies (`Network.clearBrowserCookies`).
+  pub async fn clear_cookies(&self) -> Result<()> {
+    let _value = self.cdp("Network.clearBrowserCookies", json!({})).await?;
+    Ok(())
+  }
+
+  /// Register a script to evaluate in each new document before any of
+  /// its own scripts (`Page.addScriptToEvaluateOnNewDocument`),
+  /// returning its identifier.
+  pub(crate) async fn add_init_script(&self, source: &str) -> Result<String> {
+    let response = self
+      .cdp(
+        "Page.addScriptToEvaluateOnNewDocument",
+        json!({"source": source}),
+      )
+      .await?;
+    let identifier = response
+      .get("identifier")
+      .and_then(Value::as_str)
+      .context("response is missing script identifier")?;
+    Ok(identifier.to_string())
+  }
+
+  /// Unregister a script previously registered via
+  /// [`Client::add_init_script`]
+  /// (`Page.removeScriptToEvaluateOnNewDocument`).
+  pub(crate) async fn remove_init_script(&self, identifier: &str) -> Result<()> {
+    let _value = self
+      .cdp(
+        "Page.removeScriptToEvaluateOnNewDocument",
+        json!({"identifier": identifier}),
+      )
+      .await?;
+    Ok(())
+  }
+
+  /// Evaluate a JavaScript expression in the context of the current
+  /// page (`Runtime.evaluate`), returning its result.
+  ///
+  /// Promises are awaited.
+  pub async fn evaluate(&self, expression: &str) -> Result<Value> {
+    let params = json!({
+      "expression": expression,
+      "returnByValue": true,
+      "awaitPromise": true,
+    });
+    let mut response = self.cdp("Runtime.evaluate", params).await?;
+
+    if let Some(details) = response.get("exceptionDetails") {
+      let text = details
+        .pointer("/exception/description")
+        .or_else(|| details.get("text"))
+        .and_then(Value::as_str)
+        .unwrap_or("unknown exception");
+      bail!("failed to evaluate JavaScript expression: {text}")
+    }
+
+    let value = response
+      .pointer_mut("/result/value")
+      .map(Value::take)
+      .unwrap_or(Value::Null);
+    Ok(value)
+  }
+
+  /// Print the current page to PDF (`Page.printToPDF`).
+  pub async fn print_to_pdf(&self, opts: &PdfOpts) -> Result<Vec<u8>> {
+    let response = self.cdp("Page.printToPDF", opts.to_params()).await?;
+    decode_data(&response)
+  }
+
+  /// Capture a screenshot of a region of the current page, in CSS
+  /// pixels relative to the top left corner of the page
+  /// (`Page.captureScreenshot`), in the form of a PNG image.
+  ///
+  /// The region may extend beyond the viewport.
+  pub async fn capture_region(&self, region: &Bounds) -> Result<Vec<u8>> {
+    let response = self
+      .cdp("Page.captureScreenshot", capture_params(region))
+      .await?;
+    decode_data(&response)
+  }
+
+  /// Retrieve the ID of the current page's main frame
+  /// (`Page.getFrameTree`).
+  pub(crate) async fn main_frame_id(&self) -> Result<String> {
+    let response = self.cdp("Page.getFrameTree", json!({})).await?;
+    let id = response
+      .pointer("/frameTree/frame/id")
+      .and_then(Value::as_str)
+      .context("frame tree is missing main frame ID")?;
+    Ok(id.to_string())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we create the expected parameters for device metrics
+  /// emulation.
+  #[test]
+  fn device_metrics_params() {
+    let metrics = DeviceMetrics {
+      width: 390,
+      height: 844,
+      device_scale_factor: 3.0,
+      mobile: true,
+      ..Default::default()
+    };
+    let expected = json!({
+      "width": 390,
+      "height": 844,
+      "deviceScaleFactor": 3.0,
+      "mobile": true,
+    });
+    assert_eq!(metrics.to_params(), expected);
+  }
+
+  /// Check that we create the expected parameters for geolocation
+  /// emulation.
+  #[test]
+  fn geolocation_params() {
+    let geolocation = Geolocation {
+      latitude: 52.52,
+      longitude: 13.405,
+      accuracy: 10.0,
+      ..Default::default()
+    };
+    let expected = json!({"latitude": 52.52, "longitude": 13.405, "accuracy": 10.0});
+    assert_eq!(geolocation.to_params(), expected);
+  }
+
+  /// Check that we create the expected parameters for media emulation.
+  #[test]
+  fn emulated_media_params() {
+    let media = EmulatedMedia::default();
+    assert_eq!(media.to_params(), json!({"media": "", "features": []}));
+
+    let media = EmulatedMedia {
+      media_type: Some(MediaType::from_str("print").unwrap()),
+      color_scheme: Some(ColorScheme::from_str("dark").unwrap()),
+      reduced_motion: true,
+      forced_colors: true,
+      ..Default::default()
+    };
+    let expected = json!({
+      "media": "print",
+      "features": [
+        {"name": "prefers-color-scheme", "value": "dark"},
+        {"name": "prefers-reduced-motion", "value": "reduce"},
+        {"name": "forced-colors", "value": "active"},
+      ],
+    });
+    assert_eq!(media.to_params(), expected);
+    assert!(ColorScheme::from_str("dim").is_err());
+  }
+
+  /// Check that we create the expected parameters for printing to PDF.
+  #[test]
+  fn pdf_params() {
+    let opts = PdfOpts::default();
+    let expected = json!({"landscape": false, "printBackground": false});
+    assert_eq!(opts.to_params(), expected);
+
+    let opts = PdfOpts {
+      landscape: true,
+      paper_size: Some((8.27, 11.69)),
+      ..Default::default()
+    };
+    let expected = json!({
+      "landscape": true,
+      "printBackground": false,
+      "paperWidth": 8.27,
+      "paperHeight": 11.69,
+    });
+    assert_eq!(opts.to_params(), expected);
+  }
+
+  /// Check that we create the expected parameters for capturing a
+  /// region of the page.
+  #[test]
+  fn capture_region_params() {
+    let region = Bounds {
+      x: 10.0,
+      y: 2000.5,
+      width: 300.0,
+      height: 40.0,
+      _non_exhaustive: (),
+    };
+    let expected = json!({
+      "format": "png",
+      "captureBeyondViewport": true,
+      "clip": {"x": 10.0, "y": 2000.5, "width": 300.0, "height": 40.0, "scale": 1},
+    });
+    assert_eq!(capture_params(&region), expected);
+  }
+
+  /// Make sure that we can decode binary data in DevTools responses.
+  #[test]
+  fn data_decoding() {
+    let value = json!({"data": "c2hhdmU="});
+    assert_eq!(decode_data(&value).unwrap(), b"shave");
+    assert!(decode_data(&json!({})).is_err());
+  }
+}
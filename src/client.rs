@@ -1,15 +1,31 @@
 // Copyright (C) 2024-2025 Daniel Mueller <deso@posteo.net>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+#[cfg(target_os = "linux")]
+use std::ffi::OsStr;
+use std::future::Future;
+use std::io;
+use std::io::Read as _;
+use std::io::Write as _;
 use std::net::SocketAddr;
+use std::net::TcpStream;
+use std::ops::RangeInclusive;
 use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::ensure;
 use anyhow::Context as _;
 use anyhow::Result;
 
 use chromedriver_launch::Chromedriver;
 
+use fantoccini::elements::Element;
 use fantoccini::wd::Capabilities;
+use fantoccini::wd::TimeoutConfiguration;
 use fantoccini::Client as WebdriverClient;
 use fantoccini::ClientBuilder;
 use fantoccini::Locator;
@@ -17,9 +33,41 @@ use fantoccini::Locator;
 use hyper_util::client::legacy::connect::HttpConnector;
 
 use serde_json::json;
+use serde_json::Value;
 
+use tempfile::Builder as TempFileBuilder;
+use tempfile::NamedTempFile;
 use tempfile::TempDir;
 
+use tokio::time::sleep;
+use tokio::time::timeout as with_timeout;
+
+use url::Url;
+
+use crate::command::SessionCommand;
+use crate::devtools::DevTools;
+use crate::devtools::Interceptor;
+#[cfg(target_os = "linux")]
+use crate::process::find_by_arg;
+#[cfg(target_os = "linux")]
+use crate::process::kill_by_arg;
+use crate::scroll::SCROLL_FINISH_SCRIPT;
+use crate::scroll::SCROLL_STEP_SCRIPT;
+use crate::serve::Server;
+use crate::stable::FREEZE_SCRIPT;
+use crate::stable::SETTLE_SCRIPT;
+use crate::Action;
+use crate::AutoScroll;
+use crate::BlockRules;
+use crate::Bounds;
+use crate::ConsoleMessage;
+use crate::EmulatedMedia;
+use crate::Geolocation;
+use crate::Login;
+use crate::NetworkLog;
+use crate::Proxy;
+use crate::Screenshot;
+
 
 /// A type encompassing options for capturing a screenshot.
 #[derive(Clone, Debug, Default)]
@@ -32,17 +80,83 @@ pub struct ScreenshotOpts {
   /// The selector identifying one or more elements to remove before the
   /// screenshot is captured.
   pub remove_selector: Option<String>,
+  /// Selectors identifying elements to paint over with solid boxes
+  /// before the screenshot is captured, e.g., to hide dynamic content.
+  ///
+  /// In contrast to `remove_selector`, the page's layout is left
+  /// unchanged.
+  pub mask_selectors: Vec<String>,
+  /// The CSS color to paint masked elements with (defaults to
+  /// `#ff00ff`).
+  pub mask_color: Option<String>,
   /// The selector describing the element to screenshot.
   pub selector: Option<String>,
+  /// The space to include around the element described by `selector`,
+  /// in CSS pixels, e.g., to not cut off shadows.
+  ///
+  /// The padding has to be a non-negative number.
+  pub padding: f64,
+  /// The region of the page to screenshot.
+  ///
+  /// This option is mutually exclusive with `selector`.
+  pub clip: Option<Bounds>,
+  /// Whether to render the page deterministically, so that repeated
+  /// captures of an unchanged page are identical.
+  ///
+  /// In this mode, CSS animations and transitions are disabled, the
+  /// text caret is hidden, `Date` and `Math.random` are made
+  /// deterministic, videos are paused, and web fonts are awaited.
+  pub stable: bool,
+  /// The media type and features (e.g., `prefers-color-scheme`) to
+  /// emulate for the capture.
+  pub media: EmulatedMedia,
+  /// Style sheets (as CSS text) to inject into the page once it has
+  /// loaded.
+  pub css: Vec<String>,
+  /// Interactions (e.g., clicks or key presses) to perform once the
+  /// page has loaded (and style sheets have been injected), in order.
+  pub actions: Vec<Action>,
+  /// Whether and how to scroll through the page (once actions have
+  /// been performed), to trigger lazy loading of content.
+  ///
+  /// The page is scrolled back to the top before the capture.
+  pub scroll: Option<AutoScroll>,
+  /// JavaScript code to evaluate once the page has loaded (and
+  /// actions have been performed), in order.
+  ///
+  /// The result of each evaluation (with promises being awaited) is
+  /// reported as part of [`Screenshot::script_results`].
+  pub scripts: Vec<String>,
+  /// JavaScript code to evaluate in each document before any of the
+  /// page's scripts run.
+  ///
+  /// The scripts are only registered for the duration of the capture,
+  /// including when it fails.
+  pub init_scripts: Vec<String>,
+  /// Whether to fail the capture if the page reported an uncaught
+  /// JavaScript error.
+  pub fail_on_js_error: bool,
+  /// The HTTP status codes of the main document's response to accept
+  /// (e.g., `200..=299`), failing the capture for any other.
+  ///
+  /// If `None`, any status is accepted. Otherwise, the capture also
+  /// fails if the status cannot be determined, e.g., because recording
+  /// of network activity is disabled (see
+  /// [`Builder::set_record_network`]) or because the request failed.
+  pub accept_status: Option<Vec<RangeInclusive<u16>>>,
   /// The type is non-exhaustive and open to extension.
   #[doc(hidden)]
   pub _non_exhaustive: (),
 }
 
 
+/// The locale to use if none was configured explicitly.
+const DEFAULT_LOCALE: &str = "en-US";
+
+
 /// Arguments to be passed to Chrome by default.
 /// See <https://gist.github.com/rihardn/47b8e6170dc8f57a998c90b12a3e01bb>
-static CHROME_ARGS: [&str; 54] = [
+static CHROME_ARGS: [&str; 53] = [
   // All pop-ups and calls to window.open will fail.
   "--block-new-web-contents",
   // Disable various background network services, including extension
@@ -142,7 +256,6 @@ static CHROME_ARGS: [&str; 54] = [
   // Hide scrollbars from screenshots.
   "--hide-scrollbars",
   "--incognito",
-  "--lang=en_US",
   // 0 means INFO and higher. 2 is the most verbose.
   "--log-level=0",
   // Disable reporting to UMA, but allows for collection.
@@ -172,6 +285,216 @@ static CHROME_ARGS: [&str; 54] = [
 ];
 
 
+/// The type of the Chromedriver log containing DevTools events.
+const PERFORMANCE_LOG: &str = "performance";
+/// The type of the Chromedriver log containing console messages.
+const BROWSER_LOG: &str = "browser";
+
+/// The timeout used for best-effort cleanup operations.
+const CLEANUP_TIMEOUT: Duration = Duration::from_secs(5);
+/// The interval at which to check whether killed processes vanished.
+const KILL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Script injecting a style sheet (first argument) into the page.
+const CSS_SCRIPT: &str = r#"
+  const style = document.createElement("style");
+  style.setAttribute("data-shave-css", "");
+  style.textContent = arguments[0];
+  document.documentElement.appendChild(style);
+"#;
+/// The color used for masking elements, unless configured otherwise.
+const DEFAULT_MASK_COLOR: &str = "#ff00ff";
+/// Script painting boxes over all elements matching a selector (first
+/// argument) in a given color (second argument), without affecting
+/// layout.
+const MASK_SCRIPT: &str = r#"
+  const [selector, color] = arguments;
+  const isFixed = (elem) => {
+    for (; elem instanceof Element; elem = elem.parentElement) {
+      if (getComputedStyle(elem).position === "fixed") {
+        return true;
+      }
+    }
+    return false;
+  };
+
+  for (const elem of document.querySelectorAll(selector)) {
+    const rect = elem.getBoundingClientRect();
+    const fixed = isFixed(elem);
+    const mask = document.createElement("div");
+    mask.setAttribute("data-shave-mask", "");
+    Object.assign(mask.style, {
+      position: fixed ? "fixed" : "absolute",
+      left: `${rect.left + (fixed ? 0 : window.scrollX)}px`,
+      top: `${rect.top + (fixed ? 0 : window.scrollY)}px`,
+      width: `${rect.width}px`,
+      height: `${rect.height}px`,
+      margin: "0",
+      padding: "0",
+      border: "none",
+      background: color,
+      zIndex: "2147483647",
+      pointerEvents: "none",
+    });
+    document.documentElement.appendChild(mask);
+  }
+"#;
+
+
+/// Format the Chrome argument for using the provided data directory.
+fn data_dir_arg(data_dir: &Path) -> String {
+  format!("--user-data-dir={}", data_dir.display())
+}
+
+/// Delete the WebDriver session with the given ID, using a blocking
+/// request to the WebDriver service at `addr`.
+fn delete_session(addr: SocketAddr, session_id: &str) -> io::Result<()> {
+  let mut stream = TcpStream::connect_timeout(&addr, CLEANUP_TIMEOUT)?;
+  let () = stream.set_read_timeout(Some(CLEANUP_TIMEOUT))?;
+  let () = stream.set_write_timeout(Some(CLEANUP_TIMEOUT))?;
+
+  let request = format!(
+    "DELETE /session/{session_id} HTTP/1.1\r\n\
+     Host: {addr}\r\n\
+     Content-Length: 0\r\n\
+     Connection: close\r\n\r\n"
+  );
+  let () = stream.write_all(request.as_bytes())?;
+  // Wait for the response, so that Chromedriver had a chance to shut
+  // down the browser gracefully.
+  let _count = stream.read_to_end(&mut Vec::new())?;
+  Ok(())
+}
+
+/// Shut down the Chromedriver process and kill any Chrome processes
+/// that survived it.
+///
+/// Chrome may not be part of Chromedriver's process group and so it
+/// could survive the latter's shutdown. Our unique data directory
+/// argument is used to make sure that nothing lingers. Killing these
+/// processes is only supported on Linux.
+fn kill_processes(chromedriver: Option<Chromedriver>, data_dir: Option<&TempDir>) -> Result<()> {
+  let result = if let Some(chromedriver) = chromedriver {
+    chromedriver
+      .destroy()
+      .context("failed to shut down chromedriver process")
+  } else {
+    Ok(())
+  };
+
+  #[cfg(target_os = "linux")]
+  if let Some(data_dir) = data_dir {
+    let arg = data_dir_arg(data_dir.path());
+    let kill_result =
+      kill_by_arg(OsStr::new(&arg)).context("failed to kill remaining Chrome processes");
+    return result.and(kill_result)
+  }
+  #[cfg(not(target_os = "linux"))]
+  let _data_dir = data_dir;
+  result
+}
+
+/// Check whether any (killed) Chrome processes using the provided data
+/// directory are still around.
+fn chrome_running(data_dir: Option<&TempDir>) -> bool {
+  #[cfg(target_os = "linux")]
+  if let Some(data_dir) = data_dir {
+    let arg = data_dir_arg(data_dir.path());
+    // Note that zombie processes have an empty command line and so
+    // they won't be reported here.
+    return find_by_arg(OsStr::new(&arg))
+      .map(|pids| !pids.is_empty())
+      .unwrap_or(false)
+  }
+  #[cfg(not(target_os = "linux"))]
+  let _data_dir = data_dir;
+  false
+}
+
+/// Remove the provided data directory, if any.
+fn remove_data_dir(data_dir: Option<TempDir>) -> Result<()> {
+  let running = chrome_running(data_dir.as_ref());
+
+  if let Some(data_dir) = data_dir {
+    let path = data_dir.path().to_path_buf();
+    let () = data_dir
+      .close()
+      .with_context(|| format!("failed to remove data directory `{}`", path.display()))?;
+  }
+  ensure!(!running, "Chrome processes still present after kill");
+  Ok(())
+}
+
+
+/// Write the provided HTML document to a temporary file, making
+/// relative URLs in it resolve against `base_url`.
+///
+/// The file is removed once the returned object is dropped.
+fn write_html(html: &str, base_url: &str) -> Result<(NamedTempFile, Url)> {
+  let base_url =
+    Url::parse(base_url).with_context(|| format!("failed to parse base URL `{base_url}`"))?;
+  let html = inject_base(html, base_url.as_str());
+
+  let mut file = TempFileBuilder::new()
+    .prefix("shave-")
+    .suffix(".html")
+    .tempfile()
+    .context("failed to create temporary HTML file")?;
+  let () = file
+    .write_all(html.as_bytes())
+    .context("failed to write HTML to temporary file")?;
+  let url = Url::from_file_path(file.path())
+    .map_err(|()| anyhow!("failed to convert `{}` to URL", file.path().display()))?;
+  Ok((file, url))
+}
+
+/// Make relative URLs in an HTML document resolve against `base_url`
+/// by injecting a `<base>` element.
+fn inject_base(html: &str, base_url: &str) -> String {
+  let href = base_url.replace('&', "&amp;").replace('"', "&quot;");
+  let base = format!(r#"<base href="{href}">"#);
+
+  // NB: ASCII lowercasing leaves byte offsets intact.
+  let lower = html.to_ascii_lowercase();
+  let head = lower.match_indices("<head").find(|(idx, tag)| {
+    matches!(
+      lower.as_bytes().get(idx + tag.len()),
+      Some(b'>' | b' ' | b'\t' | b'\n' | b'\r')
+    )
+  });
+  // Insert the element right after the opening `<head>` tag or, if
+  // there is none, after the doctype declaration, because anything
+  // before it would trigger quirks mode.
+  let doctype = || {
+    let start = lower.len() - lower.trim_start().len();
+    lower[start..].starts_with("<!doctype").then_some(start)
+  };
+  let idx = head
+    .map(|(idx, _tag)| idx)
+    .or_else(doctype)
+    .and_then(|start| lower[start..].find('>').map(|end| start + end + 1))
+    .unwrap_or(0);
+
+  let mut html = html.to_string();
+  let () = html.insert_str(idx, &base);
+  html
+}
+
+/// Await the provided future, subject to an optional overall timeout.
+async fn with_deadline<F, T>(timeout: Option<Duration>, future: F) -> Result<T>
+where
+  F: Future<Output = Result<T>>,
+{
+  if let Some(timeout) = timeout {
+    with_timeout(timeout, future)
+      .await
+      .with_context(|| format!("operation timed out after {timeout:?}"))?
+  } else {
+    future.await
+  }
+}
+
+
 /// A builder for configurable construction of [`Client`] objects.
 #[derive(Debug)]
 pub struct Builder {
@@ -179,6 +502,26 @@ pub struct Builder {
   user_agent: Option<String>,
   /// Whether to run in headless mode or not.
   headless: bool,
+  /// The overall timeout for operations.
+  timeout: Option<Duration>,
+  /// The proxy to use.
+  proxy: Option<Proxy>,
+  /// Whether to infer the proxy from the environment.
+  proxy_from_env: bool,
+  /// Additional HTTP headers to send with each request.
+  headers: Vec<(String, String)>,
+  /// HTTP basic authentication credentials (user name and password).
+  basic_auth: Option<(String, String)>,
+  /// The rules describing requests to block.
+  block_rules: BlockRules,
+  /// Whether to record network activity.
+  record_network: bool,
+  /// The locale to use.
+  locale: Option<String>,
+  /// The IANA time zone to use.
+  timezone: Option<String>,
+  /// The geolocation to report.
+  geolocation: Option<Geolocation>,
 }
 
 impl Builder {
@@ -194,12 +537,149 @@ impl Builder {
     self
   }
 
+  /// Set/reset the timeout to use for operations.
+  ///
+  /// The timeout applies to page loads and script executions as well
+  /// as to each [`Client`] operation (such as
+  /// [`Client::screenshot`]) as a whole.
+  pub fn set_timeout(mut self, timeout: Option<Duration>) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// Set/reset the proxy to use.
+  ///
+  /// Explicitly setting a proxy, or `None` to not use any, overrides
+  /// inference of the proxy from the environment (see
+  /// [`Builder::set_proxy_from_env`]).
+  pub fn set_proxy(mut self, proxy: Option<Proxy>) -> Self {
+    self.proxy = proxy;
+    self.proxy_from_env = false;
+    self
+  }
+
+  /// Set whether to infer the proxy from the `HTTPS_PROXY`,
+  /// `HTTP_PROXY`, and `NO_PROXY` environment variables (see
+  /// [`Proxy::from_env`]) instead of using the one set via
+  /// [`Builder::set_proxy`].
+  ///
+  /// Building the [`Client`] fails if the environment describes a
+  /// proxy that is not supported. By default, the environment is not
+  /// consulted.
+  pub fn set_proxy_from_env(mut self, proxy_from_env: bool) -> Self {
+    self.proxy_from_env = proxy_from_env;
+    self
+  }
+
+  /// Set additional HTTP headers to send with each request.
+  ///
+  /// Headers are provided as name-value pairs and sent to all hosts
+  /// that the browser contacts, not just the one being navigated to.
+  pub fn set_headers(mut self, headers: Vec<(String, String)>) -> Self {
+    self.headers = headers;
+    self
+  }
+
+  /// Set/reset HTTP basic authentication credentials, in the form of a
+  /// user name and password.
+  ///
+  /// Credentials are not sent preemptively, but only in response to
+  /// authentication challenges by servers. Answering these challenges
+  /// requires intercepting all requests.
+  pub fn set_basic_auth(mut self, basic_auth: Option<(String, String)>) -> Self {
+    self.basic_auth = basic_auth;
+    self
+  }
+
+  /// Set the rules describing requests to block.
+  pub fn set_block_rules(mut self, block_rules: BlockRules) -> Self {
+    self.block_rules = block_rules;
+    self
+  }
+
+  /// Set whether to record network activity.
+  ///
+  /// Recording is necessary for [`Client::network_log`] to report
+  /// anything and for [`Screenshot::status`] and
+  /// [`Screenshot::redirects`] to be known. It is disabled by default,
+  /// as it comes with overhead.
+  pub fn set_record_network(mut self, record_network: bool) -> Self {
+    self.record_network = record_network;
+    self
+  }
+
+  /// Set/reset the locale to use (e.g., `de-DE`).
+  ///
+  /// The locale governs the browser's UI language, the
+  /// `Accept-Language` header, and formatting via `Intl` APIs. By
+  /// default, `en-US` is used.
+  pub fn set_locale(mut self, locale: Option<String>) -> Self {
+    self.locale = locale;
+    self
+  }
+
+  /// Set/reset the IANA time zone (e.g., `Europe/Berlin`) to use,
+  /// instead of that of the host.
+  pub fn set_timezone(mut self, timezone: Option<String>) -> Self {
+    self.timezone = timezone;
+    self
+  }
+
+  /// Set/reset the geolocation to report to pages.
+  ///
+  /// Pages are granted permission to query the geolocation.
+  pub fn set_geolocation(mut self, geolocation: Option<Geolocation>) -> Self {
+    self.geolocation = geolocation;
+    self
+  }
+
+  /// Configure locale, time zone, and geolocation emulation.
+  async fn configure_emulation(&self, client: &Client) -> Result<()> {
+    if let Some(locale) = &self.locale {
+      let () = client.set_locale(Some(locale)).await?;
+    }
+    if let Some(timezone) = &self.timezone {
+      let () = client.set_timezone(Some(timezone)).await?;
+    }
+    if let Some(geolocation) = &self.geolocation {
+      let () = client.set_geolocation(Some(geolocation)).await?;
+    }
+    Ok(())
+  }
+
+  /// Configure the HTTP headers to send with each request.
+  async fn configure_headers(&self, client: &Client) -> Result<()> {
+    if !self.headers.is_empty() {
+      let () = client.set_extra_headers(&self.headers).await?;
+    }
+    Ok(())
+  }
+
+  /// Configure interception of requests.
+  async fn configure_interception(&self, client: &mut Client) -> Result<()> {
+    let interceptor = Interceptor::new(self.basic_auth.clone(), self.block_rules.clone())?;
+    if interceptor.is_empty() {
+      return Ok(())
+    }
+
+    let data_dir = client
+      .data_dir
+      .as_ref()
+      .context("data directory is no longer present")?;
+    client.devtools = Some(DevTools::connect(data_dir.path(), interceptor).await?);
+    Ok(())
+  }
+
   async fn connect(&self, addr: SocketAddr, data_dir: &Path) -> Result<WebdriverClient> {
     let webdriver_url = format!("http://{addr}");
     let mut args = Vec::from(CHROME_ARGS);
-    let data_dir_arg = format!("--user-data-dir={}", data_dir.display());
+    let data_dir_arg = data_dir_arg(data_dir);
     let () = args.push(&data_dir_arg);
 
+    let locale = self.locale.as_deref().unwrap_or(DEFAULT_LOCALE);
+    let lang_arg = format!("--lang={locale}");
+    let () = args.push(&lang_arg);
+
     let user_agent_arg;
     if let Some(user_agent) = &self.user_agent {
       user_agent_arg = format!("--user-agent={user_agent}");
@@ -211,9 +691,30 @@ impl Builder {
       let () = args.push("--headless=new");
     }
 
-    let opts = json!({"args": args});
+    let proxy = if self.proxy_from_env {
+      Proxy::from_env().context("failed to infer proxy from environment")?
+    } else {
+      self.proxy.clone()
+    };
+    let proxy_args = proxy
+      .as_ref()
+      .map(Proxy::to_chrome_args)
+      .unwrap_or_default();
+    let () = args.extend(proxy_args.iter().map(String::as_str));
+
+    let mut opts = json!({
+      "args": args,
+      "prefs": {"intl.accept_languages": locale},
+    });
+    let mut logging = json!({BROWSER_LOG: "ALL"});
+    if self.record_network {
+      // Record network events in the performance log.
+      opts["perfLoggingPrefs"] = json!({"enableNetwork": true, "enablePage": false});
+      logging[PERFORMANCE_LOG] = json!("ALL");
+    }
     let mut capabilities = Capabilities::new();
     let _val = capabilities.insert("goog:chromeOptions".to_string(), opts);
+    let _val = capabilities.insert("goog:loggingPrefs".to_string(), logging);
 
     let client = ClientBuilder::new(HttpConnector::new())
       .capabilities(capabilities)
@@ -221,21 +722,62 @@ impl Builder {
       .await
       .with_context(|| format!("failed to connect to {webdriver_url}"))?;
 
+    if let Some(timeout) = self.timeout {
+      let timeouts = TimeoutConfiguration::new(Some(timeout), Some(timeout), None);
+      let () = client
+        .update_timeouts(timeouts)
+        .await
+        .context("failed to configure WebDriver timeouts")?;
+    }
+
     Ok(client)
   }
 
   /// Create the [`Client`] object.
+  ///
+  /// If a timeout is configured, it applies to the launch of the
+  /// browser as well.
   pub async fn build(self) -> Result<Client> {
+    let timeout = self.timeout;
+    with_deadline(timeout, self.build_impl()).await
+  }
+
+  async fn build_impl(self) -> Result<Client> {
     let chromedriver = Chromedriver::launch()?;
     let data_dir = TempDir::new().context("failed to create temporary directory")?;
     let webdriver = self
       .connect(chromedriver.socket_addr(), data_dir.path())
       .await?;
-    let slf = Client {
-      chromedriver,
+    let mut slf = Client {
+      chromedriver: Some(chromedriver),
       webdriver,
-      data_dir,
+      session_id: None,
+      data_dir: Some(data_dir),
+      timeout: self.timeout,
+      record_network: self.record_network,
+      network_log: NetworkLog::default(),
+      console_log: Vec::new(),
+      server: None,
+      devtools: None,
     };
+    slf.session_id = slf
+      .webdriver
+      .session_id()
+      .await
+      .context("failed to retrieve WebDriver session ID")?;
+
+    let () = self
+      .configure_headers(&slf)
+      .await
+      .context("failed to configure HTTP headers")?;
+    let () = self
+      .configure_interception(&mut slf)
+      .await
+      .context("failed to configure request interception")?;
+    let () = self
+      .configure_emulation(&slf)
+      .await
+      .context("failed to configure emulation")?;
     Ok(slf)
   }
 }
@@ -245,20 +787,59 @@ impl Default for Builder {
     Self {
       user_agent: None,
       headless: true,
+      timeout: None,
+      proxy: None,
+      proxy_from_env: false,
+      headers: Vec::new(),
+      basic_auth: None,
+      block_rules: BlockRules::default(),
+      record_network: false,
+      locale: None,
+      timezone: None,
+      geolocation: None,
     }
   }
 }
 
 
+/// Modifications of browser state made for the duration of a capture,
+/// to be reverted once it is done.
+#[derive(Debug, Default)]
+struct Changes {
+  /// The identifiers of registered init scripts.
+  init_scripts: Vec<String>,
+  /// Whether media emulation is in effect.
+  emulated_media: bool,
+}
+
+
 /// A client for shaving data of websites.
+///
+/// A `Client` should be disposed of by means of [`Client::destroy`].
+/// However, if it is dropped instead (e.g., because of a panic), all
+/// associated resources are cleaned up on a best-effort basis.
 #[derive(Debug)]
 pub struct Client {
   /// The Chromedriver process.
-  chromedriver: Chromedriver,
+  chromedriver: Option<Chromedriver>,
   /// The WebDriver client object (communicating with the process).
   webdriver: WebdriverClient,
+  /// The ID of the WebDriver session, if it is still open.
+  session_id: Option<String>,
   /// The data directory for the Chrome instance.
-  data_dir: TempDir,
+  data_dir: Option<TempDir>,
+  /// The overall timeout for operations.
+  timeout: Option<Duration>,
+  /// Whether network activity is recorded.
+  record_network: bool,
+  /// The network requests issued during the most recent navigation.
+  network_log: NetworkLog,
+  /// The console messages logged during the most recent navigation.
+  console_log: Vec<ConsoleMessage>,
+  /// The HTTP server serving a local directory, if any.
+  server: Option<Server>,
+  /// The direct DevTools connection intercepting requests, if any.
+  devtools: Option<DevTools>,
 }
 
 impl Client {
@@ -273,47 +854,458 @@ impl Client {
     Builder::default()
   }
 
+  /// Execute a Chrome DevTools Protocol method, returning its result.
+  ///
+  /// This method is an escape hatch providing access to functionality
+  /// not otherwise exposed. See
+  /// <https://chromedevtools.github.io/devtools-protocol/> for the list
+  /// of available methods and their parameters.
+  ///
+  /// # Example
+  /// ```no_run
+  /// # use serde_json::json;
+  /// # async fn test(client: shave::Client) -> anyhow::Result<()> {
+  /// let result = client
+  ///   .cdp("Browser.getVersion", json!({}))
+  ///   .await?;
+  /// println!("{}", result["product"]);
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub async fn cdp(&self, method: &str, params: Value) -> Result<Value> {
+    self
+      .webdriver
+      .issue_cmd(SessionCommand::cdp(method, params))
+      .await
+      .with_context(|| format!("failed to execute DevTools method `{method}`"))
+  }
+
+  /// Retrieve (and clear) the entries of the Chromedriver log of the
+  /// given type.
+  async fn read_log(&self, kind: &str) -> Result<Vec<Value>> {
+    let entries = self
+      .webdriver
+      .issue_cmd(SessionCommand::log(kind))
+      .await
+      .with_context(|| format!("failed to retrieve `{kind}` log"))?;
+
+    match entries {
+      Value::Array(entries) => Ok(entries),
+      _ => Ok(Vec::new()),
+    }
+  }
+
+  /// Retrieve the log of network requests issued during the most
+  /// recent navigation, e.g., as part of [`Client::screenshot`].
+  ///
+  /// The log is empty unless recording of network activity is enabled
+  /// (see [`Builder::set_record_network`]).
+  #[inline]
+  pub fn network_log(&self) -> &NetworkLog {
+    &self.network_log
+  }
+
+  /// Retrieve the console messages (including uncaught JavaScript
+  /// errors) logged during the most recent navigation, e.g., as part of
+  /// [`Client::screenshot`].
+  #[inline]
+  pub fn console_log(&self) -> &[ConsoleMessage] {
+    &self.console_log
+  }
+
+  /// Perform an interaction with the current page.
+  pub async fn perform(&self, action: &Action) -> Result<()> {
+    let result = with_deadline(self.timeout, action.perform(&self.webdriver)).await;
+    let () = self.check_interception()?;
+    result
+  }
+
+  /// Log in via a form, as described by `login`.
+  ///
+  /// The session established this way (e.g., in the form of cookies)
+  /// is retained for subsequent operations, such as
+  /// [`Client::screenshot`].
+  pub async fn login(&self, login: &Login) -> Result<()> {
+    let result = with_deadline(self.timeout, login.perform(&self.webdriver)).await;
+    let () = self.check_interception()?;
+    result.with_context(|| format!("failed to log in at {}", login.url))
+  }
+
+  /// Check that requests are still being intercepted, if they are to
+  /// be.
+  fn check_interception(&self) -> Result<()> {
+    if let Some(devtools) = &self.devtools {
+      let () = devtools.check()?;
+    }
+    Ok(())
+  }
+
+  /// Serve the contents of the provided directory over HTTP on an
+  /// ephemeral loopback port, returning the URL of its root.
+  ///
+  /// Subsequently, URLs passed to [`Client::screenshot`] that consist
+  /// of only an absolute path (e.g., `/index.html`) refer to files
+  /// within this directory. Serving the directory over HTTP, as opposed
+  /// to loading files via `file://` URLs, makes sure that pages
+  /// relying on module scripts or CORS render as they would in
+  /// production.
+  ///
+  /// A previously served directory is no longer served.
+  pub fn serve_directory(&mut self, dir: &Path) -> Result<String> {
+    // Stop the previous server first, to not have two around at any
+    // point.
+    self.server = None;
+
+    let server = Server::new(dir)?;
+    let url = server.url("/");
+    self.server = Some(server);
+    Ok(url)
+  }
+
   /// Destroy the `Client` object, freeing up all resources.
   #[inline]
-  pub async fn destroy(self) -> Result<()> {
+  pub async fn destroy(mut self) -> Result<()> {
+    self.devtools = None;
     let () = self
       .webdriver
+      .clone()
       .close()
       .await
       .context("failed to close webdriver client connection")?;
+    self.session_id = None;
 
-    let () = self
-      .chromedriver
-      .destroy()
-      .context("failed to shut down chromedriver process")?;
+    let result = kill_processes(self.chromedriver.take(), self.data_dir.as_ref());
+    let start = Instant::now();
+    while chrome_running(self.data_dir.as_ref()) && start.elapsed() < CLEANUP_TIMEOUT {
+      let () = sleep(KILL_POLL_INTERVAL).await;
+    }
+    // Always remove the data directory, but report the first error.
+    let remove_result = remove_data_dir(self.data_dir.take());
+    result.and(remove_result)
+  }
 
-    let path = self.data_dir.path().to_path_buf();
-    let () = self
-      .data_dir
-      .close()
-      .with_context(|| format!("failed to remove data directory `{}`", path.display()))?;
+  /// Capture a screenshot in the form of a PNG image, along with
+  /// metadata about the page.
+  pub async fn screenshot(&mut self, url: &str, opts: &ScreenshotOpts) -> Result<Screenshot> {
+    let url = self.resolve_url(url);
+    let mut screenshots = self.screenshot_impl(&url, opts, false).await?;
+    screenshots.pop().context("no screenshot was captured")
+  }
+
+  /// Capture a screenshot of each element matching
+  /// [`ScreenshotOpts::selector`], in document order, along with
+  /// metadata about the page.
+  ///
+  /// The page is loaded (and prepared) only once. If no element
+  /// matches, an empty list is returned.
+  pub async fn screenshot_all(
+    &mut self,
+    url: &str,
+    opts: &ScreenshotOpts,
+  ) -> Result<Vec<Screenshot>> {
+    let url = self.resolve_url(url);
+    self.screenshot_impl(&url, opts, true).await
+  }
+
+  /// Capture a screenshot of the provided HTML document in the form of
+  /// a PNG image, along with metadata about the page.
+  ///
+  /// Relative URLs in the document (e.g., of images or style sheets)
+  /// are resolved against `base_url`, which has to be an absolute URL.
+  pub async fn screenshot_html(
+    &mut self,
+    html: &str,
+    base_url: &str,
+    opts: &ScreenshotOpts,
+  ) -> Result<Screenshot> {
+    let (_file, url) = write_html(html, base_url)?;
+    self.screenshot(url.as_str(), opts).await
+  }
+
+  /// Capture a screenshot of each element of the provided HTML
+  /// document matching [`ScreenshotOpts::selector`], in document
+  /// order, along with metadata about the page.
+  ///
+  /// Relative URLs are resolved as for [`Client::screenshot_html`],
+  /// and screenshots are reported as for [`Client::screenshot_all`].
+  pub async fn screenshot_html_all(
+    &mut self,
+    html: &str,
+    base_url: &str,
+    opts: &ScreenshotOpts,
+  ) -> Result<Vec<Screenshot>> {
+    let (_file, url) = write_html(html, base_url)?;
+    self.screenshot_all(url.as_str(), opts).await
+  }
 
+  /// Resolve a URL consisting of only an absolute path against the
+  /// served directory, if any.
+  fn resolve_url(&self, url: &str) -> String {
+    match &self.server {
+      Some(server) if url.starts_with('/') => server.url(url),
+      _ => url.to_string(),
+    }
+  }
+
+  /// Record the network activity and console messages of the most
+  /// recent navigation.
+  async fn collect_logs(&mut self) -> Result<()> {
+    self.network_log = NetworkLog::default();
+    self.console_log = Vec::new();
+
+    if self.record_network {
+      let entries = with_deadline(Some(CLEANUP_TIMEOUT), self.read_log(PERFORMANCE_LOG))
+        .await
+        .context("failed to record network activity")?;
+      self.network_log = NetworkLog::from_entries(&entries);
+    }
+
+    let entries = with_deadline(Some(CLEANUP_TIMEOUT), self.read_log(BROWSER_LOG))
+      .await
+      .context("failed to record console messages")?;
+    self.console_log = entries
+      .iter()
+      .filter_map(ConsoleMessage::from_entry)
+      .collect();
     Ok(())
   }
 
-  /// Capture a screenshot in the form of a PNG image.
-  pub async fn screenshot(&mut self, url: &str, opts: &ScreenshotOpts) -> Result<Vec<u8>> {
+  /// Revert the provided changes to browser state.
+  ///
+  /// All changes are attempted to be reverted, even if reverting one of
+  /// them failed, with the first error being reported.
+  async fn restore(&mut self, changes: Changes) -> Result<()> {
+    let Changes {
+      init_scripts,
+      emulated_media,
+    } = changes;
+
+    let mut result = Ok(());
+    if emulated_media {
+      let reset = self
+        .set_emulated_media(&EmulatedMedia::default())
+        .await
+        .context("failed to reset emulated media");
+      result = result.and(reset);
+    }
+    for identifier in init_scripts {
+      let removed = self
+        .remove_init_script(&identifier)
+        .await
+        .context("failed to remove init script");
+      result = result.and(removed);
+    }
+    result
+  }
+
+  /// Prepare the browser for and perform a capture, recording all
+  /// modifications of browser state in `changes`.
+  async fn prepare_and_capture(
+    &mut self,
+    url: &str,
+    opts: &ScreenshotOpts,
+    all: bool,
+    changes: &mut Changes,
+  ) -> Result<(String, Vec<Screenshot>)> {
+    // Fail early instead of only after the page has been loaded.
+    ensure!(
+      opts.accept_status.is_none() || self.record_network,
+      "checking the HTTP status requires recording of network activity"
+    );
+
+    // The main frame's ID is stable across navigations and identifies
+    // the requests of interest in the network log.
+    let frame_id = self.main_frame_id().await?;
+    // Discard any events from previous operations.
+    if self.record_network {
+      let _entries = self.read_log(PERFORMANCE_LOG).await?;
+    }
+    let _entries = self.read_log(BROWSER_LOG).await?;
+
+    // Scripts meant to run before any of the page's own need to be
+    // registered before navigation, but they should not outlive this
+    // capture. Each one is recorded as soon as it is registered, so
+    // that a failure to register a later one still causes earlier ones
+    // to be removed.
+    if opts.stable {
+      let identifier = self
+        .add_init_script(FREEZE_SCRIPT)
+        .await
+        .context("failed to register script for stable rendering")?;
+      let () = changes.init_scripts.push(identifier);
+    }
+    for (idx, script) in opts.init_scripts.iter().enumerate() {
+      let identifier = self
+        .add_init_script(script)
+        .await
+        .with_context(|| format!("failed to register init script #{}", idx + 1))?;
+      let () = changes.init_scripts.push(identifier);
+    }
+
+    if opts.media != EmulatedMedia::default() {
+      // Record the change up front, as a failed attempt may still have
+      // taken partial effect.
+      changes.emulated_media = true;
+      let () = self.set_emulated_media(&opts.media).await?;
+    }
+
+    let screenshots = self.capture(url, opts, all).await?;
+    Ok((frame_id, screenshots))
+  }
+
+  async fn screenshot_impl(
+    &mut self,
+    url: &str,
+    opts: &ScreenshotOpts,
+    all: bool,
+  ) -> Result<Vec<Screenshot>> {
+    let start = Instant::now();
+    let timeout = self.timeout;
+    let mut changes = Changes::default();
+    let result = with_deadline(
+      timeout,
+      self.prepare_and_capture(url, opts, all, &mut changes),
+    )
+    .await;
+    // Revert changes outside of the deadline, so that they don't leak
+    // into subsequent operations if the capture timed out.
+    let restored = with_deadline(Some(CLEANUP_TIMEOUT), self.restore(changes)).await;
+    // Make sure to record network activity and console messages even if
+    // the capture failed or timed out, as they may shed light on the
+    // cause.
+    let collected = self.collect_logs().await;
+
+    // Requests no longer being intercepted may well have caused the
+    // capture to fail, and otherwise it may not reflect the intended
+    // state.
+    let () = self.check_interception()?;
+    // An error of the capture itself is more relevant than one
+    // encountered while cleaning up after it.
+    let (frame_id, mut screenshots) = result?;
+    let () = restored.context("failed to restore browser state")?;
+    let () = collected?;
+    // All screenshots are taken of the same page, so it suffices to
+    // look at the first one.
+    let page_url = screenshots
+      .first()
+      .map(|screenshot| screenshot.url.clone())
+      .unwrap_or_default();
+    let navigation = self.network_log.navigation(&frame_id);
+    let (status, redirects) = match navigation.split_last() {
+      Some((document, redirects)) => {
+        let redirects = redirects
+          .iter()
+          .filter_map(|request| Some((request.url.clone(), request.status?)))
+          .collect::<Vec<_>>();
+        (document.status, redirects)
+      },
+      None => (None, Vec::new()),
+    };
+
+    if let Some(accept_status) = &opts.accept_status {
+      let status =
+        status.with_context(|| format!("failed to determine HTTP status of `{page_url}`"))?;
+      ensure!(
+        accept_status.iter().any(|range| range.contains(&status)),
+        "main document `{page_url}` responded with HTTP status {status}",
+      );
+    }
+
+    if opts.fail_on_js_error {
+      if let Some(error) = self.console_log.iter().find(|msg| msg.is_js_error()) {
+        bail!("page reported JavaScript error: {}", error.text)
+      }
+    }
+
+    let total = start.elapsed();
+    for screenshot in &mut screenshots {
+      screenshot.status = status;
+      screenshot.redirects = redirects.clone();
+      screenshot.timings.total = total;
+    }
+    Ok(screenshots)
+  }
+
+  /// Capture a screenshot of the element described by `selector`,
+  /// returning the image along with the captured area.
+  async fn capture_element(
+    &self,
+    element: &Element,
+    selector: &str,
+    padding: f64,
+  ) -> Result<(Vec<u8>, Bounds)> {
+    let (x, y, width, height) = element
+      .rectangle()
+      .await
+      .with_context(|| format!("failed to retrieve bounds of `{selector}`"))?;
+    let bounds = Bounds {
+      x,
+      y,
+      width,
+      height,
+      _non_exhaustive: (),
+    }
+    .pad(padding);
+
+    // Always capture the region, even without padding, so that the
+    // image's dimensions don't depend on the padding being used.
+    let image = self
+      .capture_region(&bounds)
+      .await
+      .with_context(|| format!("failed to screenshot `{selector}`"))?;
+    Ok((image, bounds))
+  }
+
+  async fn capture(
+    &mut self,
+    url: &str,
+    opts: &ScreenshotOpts,
+    all: bool,
+  ) -> Result<Vec<Screenshot>> {
     let ScreenshotOpts {
       window_size,
       await_selector,
       remove_selector,
+      mask_selectors,
+      mask_color,
       selector,
+      padding,
+      clip,
+      stable,
+      media: _,
+      css,
+      actions,
+      scroll,
+      scripts,
+      init_scripts: _,
+      fail_on_js_error: _,
+      accept_status: _,
       _non_exhaustive: (),
     } = opts;
 
+    ensure!(
+      selector.is_none() || clip.is_none(),
+      "`selector` and `clip` are mutually exclusive"
+    );
+    ensure!(
+      !all || selector.is_some(),
+      "capturing all matching elements requires a selector"
+    );
+    ensure!(
+      padding.is_finite() && *padding >= 0.0,
+      "padding {padding} is not a non-negative number"
+    );
+
     let (w, h) = window_size.unwrap_or((3840, 2160));
     let () = self.webdriver.set_window_size(w as _, h as _).await?;
 
+    let start = Instant::now();
     let () = self
       .webdriver
       .goto(url)
       .await
       .with_context(|| format!("failed to navigate to {url}"))?;
+    let navigation = start.elapsed();
 
     if let Some(await_selector) = await_selector {
       let _elem = self
@@ -324,6 +1316,59 @@ impl Client {
         .with_context(|| format!("failed to await `{await_selector}`"))?;
     }
 
+    if *stable {
+      let _value = self
+        .evaluate(SETTLE_SCRIPT)
+        .await
+        .context("failed to settle page for stable rendering")?;
+    }
+
+    for css in css {
+      let _output = self
+        .webdriver
+        .execute(CSS_SCRIPT, vec![json!(css)])
+        .await
+        .context("failed to inject style sheet")?;
+    }
+
+    for (idx, action) in actions.iter().enumerate() {
+      let () = action
+        .perform(&self.webdriver)
+        .await
+        .with_context(|| format!("failed to perform action #{}", idx + 1))?;
+    }
+
+    if let Some(scroll) = scroll {
+      // Scroll in separate steps, so that the time spent is not limited
+      // by the script timeout.
+      loop {
+        let moved = self
+          .webdriver
+          .execute(SCROLL_STEP_SCRIPT, scroll.to_args())
+          .await
+          .context("failed to scroll through page")?;
+        if moved != Value::Bool(true) {
+          break
+        }
+        let () = sleep(scroll.delay).await;
+      }
+
+      let _output = self
+        .webdriver
+        .execute(SCROLL_FINISH_SCRIPT, Vec::new())
+        .await
+        .context("failed to scroll back to top of page")?;
+    }
+
+    let mut script_results = Vec::with_capacity(scripts.len());
+    for (idx, script) in scripts.iter().enumerate() {
+      let result = self
+        .evaluate(script)
+        .await
+        .with_context(|| format!("failed to evaluate script #{}", idx + 1))?;
+      let () = script_results.push(result);
+    }
+
     if let Some(remove_selector) = remove_selector {
       // Definitely vulnerable to code injection here ¯\_(ツ)_/¯
       let js = format!(
@@ -340,29 +1385,172 @@ impl Client {
         .with_context(|| format!("failed to remove `{remove_selector}`"))?;
     }
 
-    let screenshot = if let Some(selector) = selector {
-      let element = self
+    let mask_color = mask_color.as_deref().unwrap_or(DEFAULT_MASK_COLOR);
+    for mask_selector in mask_selectors {
+      let args = vec![json!(mask_selector), json!(mask_color)];
+      let _output = self
         .webdriver
-        .find(Locator::Css(selector))
+        .execute(MASK_SCRIPT, args)
         .await
-        .with_context(|| format!("failed to find `{selector}`"))?;
+        .with_context(|| format!("failed to mask `{mask_selector}`"))?;
+    }
 
-      let screenshot = element
-        .screenshot()
-        .await
-        .with_context(|| format!("failed to screenshot `{selector}`"))?;
+    let url = self
+      .webdriver
+      .current_url()
+      .await
+      .context("failed to retrieve current URL")?
+      .to_string();
+    let title = self
+      .webdriver
+      .title()
+      .await
+      .context("failed to retrieve page title")?;
+
+    let mut captures = Vec::new();
+    if let Some(selector) = selector {
+      let elements = if all {
+        self
+          .webdriver
+          .find_all(Locator::Css(selector))
+          .await
+          .with_context(|| format!("failed to find `{selector}`"))?
+      } else {
+        let element = self
+          .webdriver
+          .find(Locator::Css(selector))
+          .await
+          .with_context(|| format!("failed to find `{selector}`"))?;
+        vec![element]
+      };
 
-      screenshot
+      for (idx, element) in elements.iter().enumerate() {
+        let start = Instant::now();
+        let (image, bounds) = self
+          .capture_element(element, selector, *padding)
+          .await
+          .with_context(|| format!("failed to capture match #{}", idx + 1))?;
+        let () = captures.push((image, Some(bounds), start.elapsed()));
+      }
+    } else if let Some(clip) = clip {
+      let start = Instant::now();
+      let image = self
+        .capture_region(clip)
+        .await
+        .with_context(|| format!("failed to screenshot region of `{url}`"))?;
+      let () = captures.push((image, Some(*clip), start.elapsed()));
     } else {
-      let screenshot = self
+      let start = Instant::now();
+      let image = self
         .webdriver
         .screenshot()
         .await
         .with_context(|| format!("failed to screenshot `{url}`"))?;
+      let () = captures.push((image, None, start.elapsed()));
+    }
+
+    let screenshots = captures
+      .into_iter()
+      .map(|(image, bounds, capture)| {
+        let mut screenshot = Screenshot::new(image);
+        screenshot.url = url.clone();
+        screenshot.title = title.clone();
+        screenshot.bounds = bounds;
+        screenshot.script_results = script_results.clone();
+        screenshot.timings.navigation = navigation;
+        screenshot.timings.capture = capture;
+        screenshot
+      })
+      .collect();
+    Ok(screenshots)
+  }
+}
+
+/// Dropping a `Client` without destroying it first shuts down the
+/// browser in the background, on a separate thread, so as to not block
+/// the current one, which may be driving an asynchronous runtime.
+/// Cleanup may not run to completion if the program exits shortly
+/// after, and errors are ignored. Use [`Client::destroy`] for
+/// deterministic cleanup.
+impl Drop for Client {
+  fn drop(&mut self) {
+    self.devtools = None;
+
+    let session_id = self.session_id.take();
+    let chromedriver = self.chromedriver.take();
+    let data_dir = self.data_dir.take();
+    let _handle = thread::spawn(move || {
+      // We can't use the asynchronous WebDriver client here, so close
+      // the session with a blocking request instead.
+      if let (Some(session_id), Some(chromedriver)) = (session_id, &chromedriver) {
+        let _result = delete_session(chromedriver.socket_addr(), &session_id);
+      }
+      let _result = kill_processes(chromedriver, data_dir.as_ref());
+      let start = Instant::now();
+      while chrome_running(data_dir.as_ref()) && start.elapsed() < CLEANUP_TIMEOUT {
+        let () = thread::sleep(KILL_POLL_INTERVAL);
+      }
+      let _result = remove_data_dir(data_dir);
+    });
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-      screenshot
-    };
 
-    Ok(screenshot)
+  /// Check that we inject a `<base>` element at the correct position
+  /// into HTML documents.
+  #[test]
+  fn base_injection() {
+    let base = r#"<base href="file:///tmp/a&amp;b/">"#;
+    let html = inject_base(
+      "<!DOCTYPE html><html><head><title>x</title></head></html>",
+      "file:///tmp/a&b/",
+    );
+    assert_eq!(
+      html,
+      format!("<!DOCTYPE html><html><head>{base}<title>x</title></head></html>")
+    );
+
+    let html = inject_base(
+      "<HTML><HEAD lang=\"en\"></HEAD><header></header></HTML>",
+      "file:///tmp/a&b/",
+    );
+    assert_eq!(
+      html,
+      format!("<HTML><HEAD lang=\"en\">{base}</HEAD><header></header></HTML>")
+    );
+
+    let html = inject_base("<!doctype html><p>hi</p>", "file:///tmp/a&b/");
+    assert_eq!(html, format!("<!doctype html>{base}<p>hi</p>"));
+
+    let html = inject_base("<header>hi</header>", "file:///tmp/a&b/");
+    assert_eq!(html, format!("{base}<header>hi</header>"));
+  }
+
+  /// Check that dropping a [`Client`] without destroying it does not
+  /// leave any Chrome processes or the data directory behind.
+  #[cfg(target_os = "linux")]
+  #[tokio::test]
+  #[ignore = "requires chromedriver and Chrome"]
+  async fn drop_cleanup() {
+    let client = Client::new().await.unwrap();
+    let data_dir = client.data_dir.as_ref().unwrap().path().to_path_buf();
+    let arg = data_dir_arg(&data_dir);
+    assert!(!find_by_arg(OsStr::new(&arg)).unwrap().is_empty());
+
+    let () = drop(client);
+    // Cleanup happens in the background, so give it some time.
+    let start = Instant::now();
+    while (data_dir.exists() || !find_by_arg(OsStr::new(&arg)).unwrap().is_empty())
+      && start.elapsed() < 2 * CLEANUP_TIMEOUT
+    {
+      let () = sleep(KILL_POLL_INTERVAL).await;
+    }
+    assert!(find_by_arg(OsStr::new(&arg)).unwrap().is_empty());
+    assert!(!data_dir.exists());
   }
 }
@@ -0,0 +1,53 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use fantoccini::wd::WebDriverCompatibleCommand;
+
+use http::Method;
+
+use serde_json::json;
+use serde_json::Value;
+
+use url::ParseError;
+use url::Url;
+
+
+/// A non-standard WebDriver command, as supported by Chromedriver,
+/// operating on the current session.
+#[derive(Debug)]
+pub(crate) struct SessionCommand {
+  /// The endpoint to send the request to, relative to the session.
+  endpoint: &'static str,
+  /// The JSON body of the request.
+  body: Value,
+}
+
+impl SessionCommand {
+  /// Create a command executing a Chrome DevTools Protocol method.
+  pub fn cdp(method: &str, params: Value) -> Self {
+    Self {
+      endpoint: "goog/cdp/execute",
+      body: json!({"cmd": method, "params": params}),
+    }
+  }
+
+  /// Create a command retrieving (and clearing) the log of the given
+  /// type, e.g., `performance`.
+  pub fn log(kind: &str) -> Self {
+    Self {
+      endpoint: "se/log",
+      body: json!({"type": kind}),
+    }
+  }
+}
+
+impl WebDriverCompatibleCommand for SessionCommand {
+  fn endpoint(&self, base_url: &Url, session_id: Option<&str>) -> Result<Url, ParseError> {
+    let session_id = session_id.unwrap_or_default();
+    base_url.join(&format!("session/{session_id}/{}", self.endpoint))
+  }
+
+  fn method_and_body(&self, _request_url: &Url) -> (Method, Option<String>) {
+    (Method::POST, Some(self.body.to_string()))
+  }
+}
@@ -0,0 +1,138 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde_json::Value;
+
+
+/// The severity level of a console message.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Level {
+  /// A debug message.
+  Debug,
+  /// An informational message.
+  Info,
+  /// A warning.
+  Warning,
+  /// An error.
+  Severe,
+}
+
+impl Level {
+  fn parse(s: &str) -> Option<Self> {
+    match s {
+      "DEBUG" | "FINE" | "FINER" | "FINEST" => Some(Self::Debug),
+      "INFO" | "CONFIG" => Some(Self::Info),
+      "WARNING" => Some(Self::Warning),
+      "SEVERE" => Some(Self::Severe),
+      _ => None,
+    }
+  }
+}
+
+impl Display for Level {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    let s = match self {
+      Self::Debug => "debug",
+      Self::Info => "info",
+      Self::Warning => "warning",
+      Self::Severe => "error",
+    };
+    f.write_str(s)
+  }
+}
+
+
+/// A message logged to the browser console, including uncaught
+/// JavaScript exceptions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConsoleMessage {
+  /// The severity level of the message.
+  pub level: Level,
+  /// The source of the message, as reported by the browser (e.g.,
+  /// `console-api` for calls to `console.log` and friends, `javascript`
+  /// for uncaught exceptions, or `network` for failed requests).
+  pub source: Option<String>,
+  /// The message text.
+  pub text: String,
+  /// The point in time at which the message got logged.
+  pub time: SystemTime,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl ConsoleMessage {
+  /// Create a `ConsoleMessage` from a Chromedriver browser log entry.
+  pub(crate) fn from_entry(entry: &Value) -> Option<Self> {
+    let level = Level::parse(entry["level"].as_str()?)?;
+    let text = entry["message"].as_str()?.to_string();
+    let millis = entry["timestamp"].as_u64().unwrap_or_default();
+
+    let slf = Self {
+      level,
+      source: entry["source"].as_str().map(str::to_string),
+      text,
+      time: UNIX_EPOCH + Duration::from_millis(millis),
+      _non_exhaustive: (),
+    };
+    Some(slf)
+  }
+
+  /// Check whether the message reports an uncaught JavaScript error.
+  pub fn is_js_error(&self) -> bool {
+    self.level == Level::Severe && self.source.as_deref() == Some("javascript")
+  }
+}
+
+impl Display for ConsoleMessage {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    write!(f, "[{}] {}", self.level, self.text)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde_json::json;
+
+
+  /// Check that we can parse browser log entries.
+  #[test]
+  fn entry_parsing() {
+    let entry = json!({
+      "level": "SEVERE",
+      "message": "https://example.com/app.js 1:7 Uncaught TypeError: x is undefined",
+      "source": "javascript",
+      "timestamp": 1700000000000u64,
+    });
+    let message = ConsoleMessage::from_entry(&entry).unwrap();
+    assert_eq!(message.level, Level::Severe);
+    assert_eq!(message.time, UNIX_EPOCH + Duration::from_secs(1700000000));
+    assert!(message.is_js_error());
+    assert_eq!(
+      message.to_string(),
+      "[error] https://example.com/app.js 1:7 Uncaught TypeError: x is undefined"
+    );
+
+    let entry = json!({
+      "level": "SEVERE",
+      "message": "https://example.com/ 10:12 \"oops\"",
+      "source": "console-api",
+      "timestamp": 1700000000000u64,
+    });
+    let message = ConsoleMessage::from_entry(&entry).unwrap();
+    assert!(!message.is_js_error());
+
+    let entry = json!({"level": "BOGUS", "message": "", "timestamp": 0});
+    assert_eq!(ConsoleMessage::from_entry(&entry), None);
+  }
+}
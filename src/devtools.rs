@@ -0,0 +1,618 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A direct connection to the browser's DevTools endpoint, used for
+//! intercepting requests (e.g., to block them).
+//!
+//! Chromedriver forwards DevTools methods, but not the events they
+//! cause. Intercepted requests are reported by means of events and
+//! stay paused until they are answered, so we have to talk to the
+//! browser ourselves for that.
+
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use anyhow::bail;
+use anyhow::Context as _;
+use anyhow::Error;
+use anyhow::Result;
+
+use futures_util::SinkExt as _;
+use futures_util::StreamExt as _;
+
+use serde_json::json;
+use serde_json::Value;
+
+use tokio::net::TcpStream;
+use tokio::spawn;
+use tokio::task::JoinHandle;
+
+use tokio_tungstenite::client_async_with_config;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::block::Blocker;
+use crate::BlockRules;
+
+
+/// The name of the file in the data directory in which Chrome stores
+/// the port and path of its DevTools endpoint.
+const ACTIVE_PORT_FILE: &str = "DevToolsActivePort";
+/// The maximum size of a WebSocket message that we accept.
+const MAX_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+
+/// Parse the contents of the `DevToolsActivePort` file into the port
+/// and path of the browser's DevTools endpoint.
+fn parse_active_port(content: &str) -> Result<(u16, &str)> {
+  let mut lines = content.lines();
+  let port = lines
+    .next()
+    .context("DevTools port information is empty")?
+    .parse()
+    .context("failed to parse DevTools port")?;
+  let path = lines
+    .next()
+    .filter(|path| path.starts_with('/'))
+    .context("DevTools port information is missing endpoint path")?;
+  Ok((port, path))
+}
+
+/// A WebSocket connection to the browser.
+#[derive(Debug)]
+struct WebSocket {
+  stream: WebSocketStream<TcpStream>,
+}
+
+impl WebSocket {
+  /// Connect to the WebSocket endpoint at `path` on the given loopback
+  /// port.
+  async fn connect(port: u16, path: &str) -> Result<Self> {
+    let stream = TcpStream::connect(("127.0.0.1", port))
+      .await
+      .with_context(|| format!("failed to connect to port {port}"))?;
+    let url = format!("ws://127.0.0.1:{port}{path}");
+    let config = WebSocketConfig::default().max_message_size(Some(MAX_MESSAGE_SIZE));
+    let (stream, _response) = client_async_with_config(url, stream, Some(config))
+      .await
+      .context("failed to perform WebSocket handshake")?;
+    Ok(Self { stream })
+  }
+
+  /// Send a text message.
+  async fn send(&mut self, text: &str) -> Result<()> {
+    self
+      .stream
+      .send(Message::text(text))
+      .await
+      .context("failed to send WebSocket message")
+  }
+
+  /// Receive the next message, returning `None` once the connection
+  /// got closed.
+  async fn recv(&mut self) -> Result<Option<String>> {
+    loop {
+      let message = match self.stream.next().await {
+        Some(result) => result.context("failed to receive WebSocket message")?,
+        None => return Ok(None),
+      };
+
+      match message {
+        Message::Text(text) => return Ok(Some(text.as_str().to_string())),
+        Message::Binary(data) => {
+          let text =
+            String::from_utf8(data.to_vec()).context("WebSocket message is not valid UTF-8")?;
+          return Ok(Some(text))
+        },
+        Message::Close(_frame) => return Ok(None),
+        // Pings are answered for us.
+        Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => (),
+      }
+    }
+  }
+}
+
+
+/// A connection to the browser's DevTools endpoint, speaking the
+/// DevTools protocol in flat session mode.
+#[derive(Debug)]
+struct Connection {
+  socket: WebSocket,
+  /// The ID to use for the next method call.
+  next_id: u64,
+  /// Events received while waiting for the result of a method call.
+  events: VecDeque<Value>,
+}
+
+impl Connection {
+  /// Issue a method call without waiting for its result, returning its
+  /// ID.
+  async fn send(&mut self, method: &str, params: Value, session_id: Option<&str>) -> Result<u64> {
+    let id = self.next_id;
+    self.next_id += 1;
+
+    let mut message = json!({"id": id, "method": method, "params": params});
+    if let Some(session_id) = session_id {
+      message["sessionId"] = json!(session_id);
+    }
+    let () = self.socket.send(&message.to_string()).await?;
+    Ok(id)
+  }
+
+  /// Receive the next message from the browser.
+  async fn recv(&mut self) -> Result<Option<Value>> {
+    if let Some(message) = self.socket.recv().await? {
+      let message = serde_json::from_str(&message).context("failed to parse DevTools message")?;
+      Ok(Some(message))
+    } else {
+      Ok(None)
+    }
+  }
+
+  /// Call a method and wait for its result.
+  async fn call(&mut self, method: &str, params: Value, session_id: Option<&str>) -> Result<Value> {
+    let id = self.send(method, params, session_id).await?;
+    loop {
+      let mut message = self
+        .recv()
+        .await?
+        .context("DevTools connection closed unexpectedly")?;
+
+      if message["id"] == id {
+        if let Some(error) = message.get("error") {
+          bail!("DevTools method `{method}` failed: {error}");
+        }
+        return Ok(message["result"].take())
+      } else if message.get("method").is_some() {
+        let () = self.events.push_back(message);
+      }
+    }
+  }
+
+  /// Retrieve the next event, returning `None` once the connection got
+  /// closed.
+  async fn next_event(&mut self) -> Result<Option<Value>> {
+    if let Some(event) = self.events.pop_front() {
+      return Ok(Some(event))
+    }
+
+    loop {
+      match self.recv().await? {
+        // Results of calls we did not wait for are of no interest.
+        Some(message) if message.get("method").is_none() => continue,
+        message => return Ok(message),
+      }
+    }
+  }
+}
+
+
+/// A description of how to handle requests intercepted by the browser.
+#[derive(Clone, Debug)]
+pub(crate) struct Interceptor {
+  /// HTTP basic authentication credentials (user name and password) to
+  /// answer authentication challenges with.
+  basic_auth: Option<(String, String)>,
+  /// The IDs of requests for which credentials were provided already.
+  ///
+  /// IDs are removed once credentials got rejected and the set is
+  /// cleared whenever a new document gets requested, so that it does
+  /// not grow without bounds.
+  authenticated: HashSet<String>,
+  /// The blocker deciding which requests to block.
+  blocker: Blocker,
+}
+
+impl Interceptor {
+  /// Create a new `Interceptor` answering authentication challenges
+  /// with the provided credentials, if any, and blocking requests as
+  /// described by `block_rules`.
+  pub(crate) fn new(basic_auth: Option<(String, String)>, block_rules: BlockRules) -> Result<Self> {
+    let slf = Self {
+      basic_auth,
+      authenticated: HashSet::new(),
+      blocker: Blocker::new(block_rules)?,
+    };
+    Ok(slf)
+  }
+
+  /// Check whether there is nothing to intercept.
+  pub(crate) fn is_empty(&self) -> bool {
+    self.basic_auth.is_none() && self.blocker.is_empty()
+  }
+
+  /// Create the parameters for the `Fetch.enable` method.
+  fn fetch_params(&self) -> Value {
+    let patterns = if self.basic_auth.is_some() {
+      // Authentication challenges are only reported for requests that
+      // are intercepted.
+      vec![json!({"urlPattern": "*"})]
+    } else {
+      self.blocker.fetch_patterns()
+    };
+
+    json!({
+      "patterns": patterns,
+      "handleAuthRequests": self.basic_auth.is_some(),
+    })
+  }
+
+  /// Enable interception for the target attached to by the session with
+  /// the given ID.
+  async fn enable(&self, conn: &mut Connection, session_id: &str) -> Result<()> {
+    let _result = conn
+      .call("Fetch.enable", self.fetch_params(), Some(session_id))
+      .await?;
+    // Make sure to also intercept requests of out-of-process frames and
+    // workers, which are separate targets.
+    let _result = conn
+      .call(
+        "Target.setAutoAttach",
+        json!({"autoAttach": true, "waitForDebuggerOnStart": true, "flatten": true}),
+        Some(session_id),
+      )
+      .await?;
+    Ok(())
+  }
+
+  /// Answer an authentication challenge.
+  fn auth_response(&mut self, params: &Value) -> Value {
+    let request_id = params["requestId"].as_str().unwrap_or_default();
+    let source = params["authChallenge"]["source"].as_str();
+
+    match &self.basic_auth {
+      Some((user, password)) if source == Some("Server") => {
+        // Only provide credentials once per request, as the challenge
+        // is repeated if they are not accepted.
+        if self.authenticated.insert(request_id.to_string()) {
+          json!({
+            "response": "ProvideCredentials",
+            "username": user,
+            "password": password,
+          })
+        } else {
+          let _present = self.authenticated.remove(request_id);
+          json!({"response": "CancelAuth"})
+        }
+      },
+      _ => json!({"response": "CancelAuth"}),
+    }
+  }
+
+  /// Handle an event reported by the browser.
+  async fn handle(&mut self, conn: &mut Connection, event: &Value) -> Result<()> {
+    let session_id = event["sessionId"].as_str();
+    let params = &event["params"];
+    let request_id = &params["requestId"];
+
+    match event["method"].as_str().unwrap_or_default() {
+      "Fetch.requestPaused" => {
+        let url = params["request"]["url"].as_str().unwrap_or_default();
+        let resource_type = params["resourceType"].as_str().unwrap_or_default();
+        // Requests for which credentials got accepted are not reported
+        // again, so forget about all of them on navigation.
+        if resource_type == "Document" {
+          let () = self.authenticated.clear();
+        }
+        let _id = if self.blocker.is_blocked(url, resource_type) {
+          conn
+            .send(
+              "Fetch.failRequest",
+              json!({"requestId": request_id, "errorReason": "BlockedByClient"}),
+              session_id,
+            )
+            .await?
+        } else {
+          conn
+            .send(
+              "Fetch.continueRequest",
+              json!({"requestId": request_id}),
+              session_id,
+            )
+            .await?
+        };
+      },
+      "Fetch.authRequired" => {
+        let response = self.auth_response(params);
+        let _id = conn
+          .send(
+            "Fetch.continueWithAuth",
+            json!({"requestId": request_id, "authChallengeResponse": response}),
+            session_id,
+          )
+          .await?;
+      },
+      "Target.attachedToTarget" => {
+        let child = params["sessionId"].as_str().unwrap_or_default();
+        // The target is paused until we tell it to run, so interception
+        // is in place before it issues its first request. The methods
+        // are processed in order, so there is no need to wait for their
+        // results.
+        let _id = conn
+          .send("Fetch.enable", self.fetch_params(), Some(child))
+          .await?;
+        let _id = conn
+          .send(
+            "Target.setAutoAttach",
+            json!({"autoAttach": true, "waitForDebuggerOnStart": true, "flatten": true}),
+            Some(child),
+          )
+          .await?;
+        let _id = conn
+          .send("Runtime.runIfWaitingForDebugger", json!({}), Some(child))
+          .await?;
+      },
+      _ => (),
+    }
+    Ok(())
+  }
+}
+
+
+/// Handle events until the connection is closed, recording the error
+/// that caused it to be closed prematurely, if any, in `failure`.
+async fn run(mut conn: Connection, mut interceptor: Interceptor, failure: Arc<OnceLock<Error>>) {
+  let result = async {
+    while let Some(event) = conn.next_event().await? {
+      let () = interceptor.handle(&mut conn, &event).await?;
+    }
+    Result::<()>::Ok(())
+  }
+  .await;
+
+  if let Err(err) = result {
+    // Closing the connection detaches us from all targets, which
+    // disables interception, so that requests don't stay paused
+    // forever.
+    let () = drop(conn);
+    let _result = failure.set(err);
+  }
+}
+
+
+/// A connection to the browser's DevTools endpoint, intercepting
+/// requests in the background for as long as it is alive.
+#[derive(Debug)]
+pub(crate) struct DevTools {
+  task: JoinHandle<()>,
+  /// The error that stopped interception, if any.
+  failure: Arc<OnceLock<Error>>,
+}
+
+impl DevTools {
+  /// Connect to the DevTools endpoint of the browser using the given
+  /// data directory and start intercepting requests of all its pages.
+  pub(crate) async fn connect(data_dir: &Path, interceptor: Interceptor) -> Result<Self> {
+    let path = data_dir.join(ACTIVE_PORT_FILE);
+    let content =
+      read_to_string(&path).with_context(|| format!("failed to read `{}`", path.display()))?;
+    let (port, path) = parse_active_port(&content)?;
+    let socket = WebSocket::connect(port, path)
+      .await
+      .context("failed to connect to DevTools endpoint")?;
+    let mut conn = Connection {
+      socket,
+      next_id: 0,
+      events: VecDeque::new(),
+    };
+
+    // Attach to all pages automatically, including ones opened later
+    // on (e.g., popups or via `window.open`). These are paused until
+    // interception is in place for them.
+    let _result = conn
+      .call("Target.setDiscoverTargets", json!({"discover": true}), None)
+      .await?;
+    let _result = conn
+      .call(
+        "Target.setAutoAttach",
+        json!({"autoAttach": true, "waitForDebuggerOnStart": true, "flatten": true}),
+        None,
+      )
+      .await?;
+
+    // Pages existing already are attached to as part of the above. Make
+    // sure that interception is in place for them before returning, as
+    // opposed to just eventually.
+    let mut attached = HashSet::new();
+    let events = conn.events.drain(..).collect::<Vec<_>>();
+    for event in events {
+      if event["method"] != "Target.attachedToTarget" {
+        let () = conn.events.push_back(event);
+        continue
+      }
+
+      let params = &event["params"];
+      let session_id = params["sessionId"]
+        .as_str()
+        .context("DevTools session ID is missing")?;
+      let target_id = params["targetInfo"]["targetId"]
+        .as_str()
+        .unwrap_or_default();
+      let _inserted = attached.insert(target_id.to_string());
+      let () = interceptor.enable(&mut conn, session_id).await?;
+      if params["waitingForDebugger"] == true {
+        let _result = conn
+          .call(
+            "Runtime.runIfWaitingForDebugger",
+            json!({}),
+            Some(session_id),
+          )
+          .await?;
+      }
+    }
+
+    // Older browsers may not attach to existing pages automatically, so
+    // attach to any that got missed explicitly.
+    let targets = conn.call("Target.getTargets", json!({}), None).await?;
+    let pages = targets["targetInfos"]
+      .as_array()
+      .into_iter()
+      .flatten()
+      .filter(|target| {
+        let target_id = target["targetId"].as_str().unwrap_or_default();
+        target["type"] == "page" && !attached.contains(target_id)
+      })
+      .cloned()
+      .collect::<Vec<_>>();
+
+    for page in pages {
+      let target_id = &page["targetId"];
+      let result = conn
+        .call(
+          "Target.attachToTarget",
+          json!({"targetId": target_id, "flatten": true}),
+          None,
+        )
+        .await?;
+      let session_id = result["sessionId"]
+        .as_str()
+        .context("DevTools session ID is missing")?
+        .to_string();
+      let () = interceptor.enable(&mut conn, &session_id).await?;
+    }
+
+    let failure = Arc::new(OnceLock::new());
+    let task = spawn(run(conn, interceptor, Arc::clone(&failure)));
+    Ok(Self { task, failure })
+  }
+
+  /// Check whether requests are still being intercepted as intended,
+  /// reporting the error that stopped interception otherwise.
+  pub(crate) fn check(&self) -> Result<()> {
+    if let Some(err) = self.failure.get() {
+      bail!("failed to intercept requests: {err:#}")
+    }
+    Ok(())
+  }
+}
+
+impl Drop for DevTools {
+  fn drop(&mut self) {
+    let () = self.task.abort();
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we can parse the contents of the `DevToolsActivePort`
+  /// file.
+  #[test]
+  fn active_port_parsing() {
+    let content = "39241\n/devtools/browser/6a1f6a5e-2b3c-4d5e-8f90-123456789abc\n";
+    let (port, path) = parse_active_port(content).unwrap();
+    assert_eq!(port, 39241);
+    assert_eq!(
+      path,
+      "/devtools/browser/6a1f6a5e-2b3c-4d5e-8f90-123456789abc"
+    );
+
+    assert!(parse_active_port("").is_err());
+    assert!(parse_active_port("39241\n").is_err());
+    assert!(parse_active_port("port\n/devtools/browser/x").is_err());
+  }
+
+  /// Check that we can exchange messages with a WebSocket server.
+  #[tokio::test]
+  async fn websocket_messages() {
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = spawn(async move {
+      let (stream, _addr) = listener.accept().await.unwrap();
+      let mut stream = accept_async(stream).await.unwrap();
+      let () = stream.send(Message::Ping("p".into())).await.unwrap();
+      let () = stream.send(Message::text("Hello")).await.unwrap();
+
+      let message = stream.next().await.unwrap().unwrap();
+      assert_eq!(message, Message::Pong("p".into()));
+      let message = stream.next().await.unwrap().unwrap();
+      assert_eq!(message, Message::text("hi"));
+      let () = stream.close(None).await.unwrap();
+    });
+
+    let mut socket = WebSocket::connect(port, "/devtools/browser/x")
+      .await
+      .unwrap();
+    assert_eq!(socket.recv().await.unwrap().as_deref(), Some("Hello"));
+    let () = socket.send("hi").await.unwrap();
+    assert_eq!(socket.recv().await.unwrap(), None);
+    let () = server.await.unwrap();
+  }
+
+  /// Make sure that we reject WebSocket handshake responses not
+  /// acknowledging our key.
+  #[tokio::test]
+  async fn websocket_handshake_validation() {
+    use tokio::io::AsyncBufReadExt as _;
+    use tokio::io::AsyncWriteExt as _;
+    use tokio::io::BufReader;
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = spawn(async move {
+      let (stream, _addr) = listener.accept().await.unwrap();
+      let mut stream = BufReader::new(stream);
+      let mut head = String::new();
+      while !head.ends_with("\r\n\r\n") {
+        let _count = stream.read_line(&mut head).await.unwrap();
+      }
+
+      let response = "HTTP/1.1 101 Switching Protocols\r\n\
+                      Upgrade: websocket\r\n\
+                      Connection: Upgrade\r\n\
+                      Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n";
+      let () = stream.write_all(response.as_bytes()).await.unwrap();
+    });
+
+    let result = WebSocket::connect(port, "/devtools/browser/x").await;
+    assert!(result.is_err());
+    let () = server.await.unwrap();
+  }
+
+  /// Check that we answer authentication challenges as expected.
+  #[test]
+  fn auth_responses() {
+    let challenge = |id: &str, source: &str| {
+      json!({
+        "requestId": id,
+        "authChallenge": {"source": source, "origin": "https://example.com"},
+      })
+    };
+
+    let mut interceptor = Interceptor::new(None, BlockRules::default()).unwrap();
+    assert!(interceptor.is_empty());
+    assert_eq!(
+      interceptor.auth_response(&challenge("1", "Server")),
+      json!({"response": "CancelAuth"})
+    );
+
+    let credentials = ("alice".to_string(), "hunter2".to_string());
+    let mut interceptor = Interceptor::new(Some(credentials), BlockRules::default()).unwrap();
+    assert_eq!(
+      interceptor.auth_response(&challenge("1", "Server")),
+      json!({"response": "ProvideCredentials", "username": "alice", "password": "hunter2"})
+    );
+    // Credentials that were rejected are not provided again.
+    assert_eq!(
+      interceptor.auth_response(&challenge("1", "Server")),
+      json!({"response": "CancelAuth"})
+    );
+    assert!(interceptor.authenticated.is_empty());
+    assert_eq!(
+      interceptor.auth_response(&challenge("2", "Proxy")),
+      json!({"response": "CancelAuth"})
+    );
+  }
+}
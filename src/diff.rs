@@ -0,0 +1,573 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Functionality for visually comparing screenshots.
+//!
+//! The comparison is modeled after
+//! [pixelmatch](https://github.com/mapbox/pixelmatch): pixels are
+//! compared by their perceived color difference in the YIQ color space
+//! and differences caused by anti-aliasing can be told apart from
+//! actual ones.
+
+use std::io::Cursor;
+
+use anyhow::ensure;
+use anyhow::Context as _;
+use anyhow::Result;
+
+use png::BitDepth;
+use png::ColorType;
+use png::Decoder;
+use png::Encoder;
+use png::Transformations;
+
+
+/// The maximum possible YIQ color difference between two pixels.
+const MAX_DELTA: f64 = 35215.0;
+/// The color used for highlighting differing pixels.
+const DIFF_COLOR: [u8; 3] = [255, 0, 0];
+/// The color used for highlighting pixels differing only due to
+/// anti-aliasing.
+const AA_COLOR: [u8; 3] = [255, 255, 0];
+
+
+/// A rectangular region of an image, in pixels.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Region {
+  /// The horizontal position of the top left corner.
+  pub x: u32,
+  /// The vertical position of the top left corner.
+  pub y: u32,
+  /// The width of the region.
+  pub width: u32,
+  /// The height of the region.
+  pub height: u32,
+}
+
+impl Region {
+  /// Check whether the region contains the given pixel.
+  fn contains(&self, x: u32, y: u32) -> bool {
+    (self.x..self.x.saturating_add(self.width)).contains(&x)
+      && (self.y..self.y.saturating_add(self.height)).contains(&y)
+  }
+}
+
+
+/// A type encompassing options for comparing screenshots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffOpts {
+  /// The per-pixel color difference to tolerate, ranging from `0.0`
+  /// (exact match required) to `1.0` (any difference tolerated). Values
+  /// outside of this range are rejected.
+  pub threshold: f64,
+  /// Whether to count pixels that differ only due to anti-aliasing as
+  /// mismatches.
+  pub include_anti_aliasing: bool,
+  /// Regions of the images to ignore (e.g., because they show dynamic
+  /// content).
+  pub ignore_regions: Vec<Region>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Default for DiffOpts {
+  fn default() -> Self {
+    Self {
+      threshold: 0.1,
+      include_anti_aliasing: false,
+      ignore_regions: Vec::new(),
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// The result of comparing two screenshots.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diff {
+  /// A PNG image highlighting differing pixels in red (and those
+  /// differing only due to anti-aliasing in yellow) on top of a faded
+  /// version of the first image.
+  pub image: Vec<u8>,
+  /// The number of differing pixels.
+  pub mismatched: u64,
+  /// The number of pixels compared (that is, not ignored).
+  pub compared: u64,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Diff {
+  /// Retrieve the percentage of compared pixels that differ.
+  pub fn mismatch_percentage(&self) -> f64 {
+    if self.compared == 0 {
+      0.0
+    } else {
+      self.mismatched as f64 / self.compared as f64 * 100.0
+    }
+  }
+
+  /// Check whether the compared images match, i.e., no pixel differs.
+  #[inline]
+  pub fn is_match(&self) -> bool {
+    self.mismatched == 0
+  }
+}
+
+
+/// A decoded RGBA image.
+struct Image {
+  width: u32,
+  height: u32,
+  data: Vec<u8>,
+}
+
+impl Image {
+  /// Decode a PNG image.
+  fn decode(png: &[u8]) -> Result<Self> {
+    let mut decoder = Decoder::new(Cursor::new(png));
+    let () = decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().context("failed to read PNG header")?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+      .next_frame(&mut buffer)
+      .context("failed to decode PNG image data")?;
+    let () = buffer.truncate(info.buffer_size());
+
+    let data = match info.color_type {
+      ColorType::Rgba => buffer,
+      ColorType::Rgb => buffer
+        .chunks_exact(3)
+        .flat_map(|px| [px[0], px[1], px[2], 255])
+        .collect(),
+      ColorType::GrayscaleAlpha => buffer
+        .chunks_exact(2)
+        .flat_map(|px| [px[0], px[0], px[0], px[1]])
+        .collect(),
+      ColorType::Grayscale => buffer.iter().flat_map(|px| [*px, *px, *px, 255]).collect(),
+      ColorType::Indexed => unreachable!("indexed PNG image was not expanded"),
+    };
+
+    let slf = Self {
+      width: info.width,
+      height: info.height,
+      data,
+    };
+    Ok(slf)
+  }
+
+  /// Retrieve the pixel at the given position.
+  fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+    let idx = (y as usize * self.width as usize + x as usize) * 4;
+    [
+      self.data[idx],
+      self.data[idx + 1],
+      self.data[idx + 2],
+      self.data[idx + 3],
+    ]
+  }
+}
+
+
+/// Blend a color channel value with white, based on its alpha.
+fn blend(c: f64, alpha: f64) -> f64 {
+  255.0 + (c - 255.0) * alpha
+}
+
+/// Convert a pixel into YIQ color space, blending it with white.
+fn yiq(pixel: [u8; 4]) -> (f64, f64, f64) {
+  let [r, g, b, a] = pixel.map(f64::from);
+  let (r, g, b) = if a < 255.0 {
+    let a = a / 255.0;
+    (blend(r, a), blend(g, a), blend(b, a))
+  } else {
+    (r, g, b)
+  };
+
+  let y = r * 0.29889531 + g * 0.58662247 + b * 0.11448223;
+  let i = r * 0.59597799 - g * 0.27417610 - b * 0.32180189;
+  let q = r * 0.21147017 - g * 0.52261711 + b * 0.31114694;
+  (y, i, q)
+}
+
+/// Calculate the perceived color difference between two pixels.
+///
+/// The sign of the result indicates whether the first pixel is darker
+/// (negative) or lighter (positive) than the second one.
+fn color_delta(pixel1: [u8; 4], pixel2: [u8; 4], y_only: bool) -> f64 {
+  if pixel1 == pixel2 {
+    return 0.0
+  }
+
+  let (y1, i1, q1) = yiq(pixel1);
+  let (y2, i2, q2) = yiq(pixel2);
+  let y = y1 - y2;
+  if y_only {
+    return y
+  }
+
+  let i = i1 - i2;
+  let q = q1 - q2;
+  let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+  if y1 > y2 {
+    -delta
+  } else {
+    delta
+  }
+}
+
+/// Iterate over the positions of all neighbors of the given pixel,
+/// also reporting whether the pixel is at the image's edge.
+fn neighbors(image: &Image, x: u32, y: u32) -> (bool, impl Iterator<Item = (u32, u32)>) {
+  let x0 = x.saturating_sub(1);
+  let y0 = y.saturating_sub(1);
+  let x2 = (x + 1).min(image.width - 1);
+  let y2 = (y + 1).min(image.height - 1);
+  let edge = x == x0 || x == x2 || y == y0 || y == y2;
+
+  let iter = (y0..=y2)
+    .flat_map(move |ny| (x0..=x2).map(move |nx| (nx, ny)))
+    .filter(move |pos| *pos != (x, y));
+  (edge, iter)
+}
+
+/// Check whether the given pixel has more than two identical neighbors.
+fn has_many_siblings(image: &Image, x: u32, y: u32) -> bool {
+  let pixel = image.pixel(x, y);
+  let (edge, neighbors) = neighbors(image, x, y);
+  let mut zeroes = usize::from(edge);
+
+  for (nx, ny) in neighbors {
+    if image.pixel(nx, ny) == pixel {
+      zeroes += 1;
+      if zeroes > 2 {
+        return true
+      }
+    }
+  }
+  false
+}
+
+/// Check whether the given pixel of `image` is likely the result of
+/// anti-aliasing, as opposed to an actual difference to `other`.
+///
+/// See "Anti-aliased Pixel and Intensity Slope Detector" by V. Vysniauskas, 2009.
+fn is_anti_aliased(image: &Image, other: &Image, x: u32, y: u32) -> bool {
+  let pixel = image.pixel(x, y);
+  let (edge, neighbors) = neighbors(image, x, y);
+  let mut zeroes = usize::from(edge);
+  let mut min = (0.0, x, y);
+  let mut max = (0.0, x, y);
+
+  for (nx, ny) in neighbors {
+    let delta = color_delta(pixel, image.pixel(nx, ny), true);
+    if delta == 0.0 {
+      zeroes += 1;
+      // If there are more than two equal siblings, it's definitely
+      // not anti-aliasing.
+      if zeroes > 2 {
+        return false
+      }
+    } else if delta < min.0 {
+      min = (delta, nx, ny);
+    } else if delta > max.0 {
+      max = (delta, nx, ny);
+    }
+  }
+
+  // If there are no both darker and brighter pixels among siblings,
+  // it's not anti-aliasing.
+  if min.0 == 0.0 || max.0 == 0.0 {
+    return false
+  }
+
+  // If either the darkest or the brightest pixel has more than two
+  // equal siblings in both images, it's likely anti-aliasing.
+  let (_, min_x, min_y) = min;
+  let (_, max_x, max_y) = max;
+  (has_many_siblings(image, min_x, min_y) && has_many_siblings(other, min_x, min_y))
+    || (has_many_siblings(image, max_x, max_y) && has_many_siblings(other, max_x, max_y))
+}
+
+/// Encode RGBA data as PNG image.
+fn encode(width: u32, height: u32, data: &[u8]) -> Result<Vec<u8>> {
+  let mut png = Vec::new();
+  let mut encoder = Encoder::new(&mut png, width, height);
+  let () = encoder.set_color(ColorType::Rgba);
+  let () = encoder.set_depth(BitDepth::Eight);
+  let mut writer = encoder
+    .write_header()
+    .context("failed to write PNG header")?;
+  let () = writer
+    .write_image_data(data)
+    .context("failed to encode PNG image data")?;
+  let () = writer.finish().context("failed to finish PNG image")?;
+  Ok(png)
+}
+
+
+/// Compare two PNG images, e.g., as captured by
+/// [`Client::screenshot`][crate::Client::screenshot].
+///
+/// Images of different sizes are compared based on their top left
+/// corners, with all pixels present in only one of them considered
+/// different.
+pub fn diff(png1: &[u8], png2: &[u8], opts: &DiffOpts) -> Result<Diff> {
+  let DiffOpts {
+    threshold,
+    include_anti_aliasing,
+    ignore_regions,
+    _non_exhaustive: (),
+  } = opts;
+
+  ensure!(
+    (0.0..=1.0).contains(threshold),
+    "threshold {threshold} is not in range [0.0, 1.0]"
+  );
+
+  let image1 = Image::decode(png1).context("failed to decode first image")?;
+  let image2 = Image::decode(png2).context("failed to decode second image")?;
+
+  let width = image1.width.max(image2.width);
+  let height = image1.height.max(image2.height);
+  let max_delta = MAX_DELTA * threshold * threshold;
+  // Anti-aliasing detection inspects pixels up to two positions away
+  // in both images. If their sizes differ, only perform it where this
+  // entire neighborhood is contained in both.
+  let anti_aliasing_checkable = |x: u32, y: u32| {
+    (image1.width == image2.width || x + 2 < image1.width.min(image2.width))
+      && (image1.height == image2.height || y + 2 < image1.height.min(image2.height))
+  };
+  let mut output = Vec::with_capacity(width as usize * height as usize * 4);
+  let mut mismatched = 0;
+  let mut compared = 0;
+
+  for y in 0..height {
+    for x in 0..width {
+      let in1 = x < image1.width && y < image1.height;
+      let in2 = x < image2.width && y < image2.height;
+      let ignored = ignore_regions.iter().any(|region| region.contains(x, y));
+
+      let color = if !ignored {
+        compared += 1;
+
+        if in1 && in2 {
+          let pixel1 = image1.pixel(x, y);
+          let pixel2 = image2.pixel(x, y);
+          let delta = color_delta(pixel1, pixel2, false);
+
+          if delta.abs() <= max_delta {
+            None
+          } else if !include_anti_aliasing
+            && anti_aliasing_checkable(x, y)
+            && (is_anti_aliased(&image1, &image2, x, y) || is_anti_aliased(&image2, &image1, x, y))
+          {
+            Some(AA_COLOR)
+          } else {
+            mismatched += 1;
+            Some(DIFF_COLOR)
+          }
+        } else {
+          mismatched += 1;
+          Some(DIFF_COLOR)
+        }
+      } else {
+        None
+      };
+
+      let [r, g, b] = color.unwrap_or_else(|| {
+        // Draw a faded grayscale version of whatever is present.
+        let pixel = if in1 {
+          image1.pixel(x, y)
+        } else {
+          image2.pixel(x, y)
+        };
+        let (y, _, _) = yiq(pixel);
+        let gray = blend(y, 0.1) as u8;
+        [gray, gray, gray]
+      });
+      let () = output.extend_from_slice(&[r, g, b, 255]);
+    }
+  }
+
+  let diff = Diff {
+    image: encode(width, height, &output)?,
+    mismatched,
+    compared,
+    _non_exhaustive: (),
+  };
+  Ok(diff)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Create a PNG image of the given size, using `f` to determine the
+  /// color of each pixel.
+  fn png<F>(width: u32, height: u32, f: F) -> Vec<u8>
+  where
+    F: Fn(u32, u32) -> [u8; 4],
+  {
+    let data = (0..height)
+      .flat_map(|y| (0..width).map(move |x| (x, y)))
+      .flat_map(|(x, y)| f(x, y))
+      .collect::<Vec<_>>();
+    encode(width, height, &data).unwrap()
+  }
+
+
+  /// Check that identical images match.
+  #[test]
+  fn identical() {
+    let image = png(8, 8, |x, y| [(x * 30) as u8, (y * 30) as u8, 128, 255]);
+    let diff = diff(&image, &image, &DiffOpts::default()).unwrap();
+    assert!(diff.is_match());
+    assert_eq!(diff.compared, 64);
+    assert_eq!(diff.mismatch_percentage(), 0.0);
+
+    let diff = Image::decode(&diff.image).unwrap();
+    assert_eq!((diff.width, diff.height), (8, 8));
+  }
+
+  /// Check that we detect differing pixels, honoring the threshold.
+  #[test]
+  fn differences() {
+    let white = png(10, 10, |_, _| [255, 255, 255, 255]);
+    let marked = png(10, 10, |x, y| {
+      if x >= 5 && y >= 5 {
+        [0, 0, 0, 255]
+      } else if x == 0 && y == 0 {
+        [250, 250, 250, 255]
+      } else {
+        [255, 255, 255, 255]
+      }
+    });
+
+    let diff = diff(&white, &marked, &DiffOpts::default()).unwrap();
+    assert_eq!(diff.mismatched, 25);
+    assert_eq!(diff.mismatch_percentage(), 25.0);
+
+    let image = Image::decode(&diff.image).unwrap();
+    assert_eq!(image.pixel(7, 7), [255, 0, 0, 255]);
+    assert_ne!(image.pixel(0, 0), [255, 0, 0, 255]);
+
+    let opts = DiffOpts {
+      threshold: 0.0,
+      ..Default::default()
+    };
+    let diff = self::diff(&white, &marked, &opts).unwrap();
+    assert_eq!(diff.mismatched, 26);
+
+    for threshold in [-0.1, 1.1, f64::NAN] {
+      let opts = DiffOpts {
+        threshold,
+        ..Default::default()
+      };
+      assert!(self::diff(&white, &marked, &opts).is_err());
+    }
+  }
+
+  /// Make sure that ignored regions are excluded from the comparison.
+  #[test]
+  fn ignored_regions() {
+    let white = png(10, 10, |_, _| [255, 255, 255, 255]);
+    let marked = png(10, 10, |x, _| {
+      if x >= 5 {
+        [0, 0, 0, 255]
+      } else {
+        [255, 255, 255, 255]
+      }
+    });
+    let opts = DiffOpts {
+      ignore_regions: vec![Region {
+        x: 5,
+        y: 0,
+        width: 5,
+        height: 8,
+      }],
+      ..Default::default()
+    };
+    let diff = diff(&white, &marked, &opts).unwrap();
+    assert_eq!(diff.compared, 60);
+    assert_eq!(diff.mismatched, 10);
+  }
+
+  /// Check that pixels present in only one image count as different.
+  #[test]
+  fn size_mismatch() {
+    let small = png(4, 4, |_, _| [255, 255, 255, 255]);
+    let large = png(4, 6, |_, _| [255, 255, 255, 255]);
+    let diff = diff(&small, &large, &DiffOpts::default()).unwrap();
+    assert_eq!(diff.compared, 24);
+    assert_eq!(diff.mismatched, 8);
+  }
+
+  /// Check that we tell apart anti-aliasing from actual differences.
+  #[test]
+  fn anti_aliasing() {
+    // A diagonal edge between a black and a white area, with a gray
+    // anti-aliased seam in the second image.
+    let sharp = png(8, 8, |x, y| {
+      if x > y {
+        [0, 0, 0, 255]
+      } else {
+        [255, 255, 255, 255]
+      }
+    });
+    let smooth = png(8, 8, |x, y| {
+      if x > y {
+        [0, 0, 0, 255]
+      } else if x == y {
+        [128, 128, 128, 255]
+      } else {
+        [255, 255, 255, 255]
+      }
+    });
+
+    let diff = diff(&sharp, &smooth, &DiffOpts::default()).unwrap();
+    assert!(diff.is_match(), "{}", diff.mismatched);
+
+    let opts = DiffOpts {
+      include_anti_aliasing: true,
+      ..Default::default()
+    };
+    let diff = self::diff(&sharp, &smooth, &opts).unwrap();
+    assert_eq!(diff.mismatched, 8);
+  }
+
+  /// Make sure that anti-aliasing detection does not access pixels
+  /// outside of the smaller of two differently sized images.
+  #[test]
+  fn anti_aliasing_size_mismatch() {
+    let sharp = png(8, 8, |x, y| {
+      if x > y {
+        [0, 0, 0, 255]
+      } else {
+        [255, 255, 255, 255]
+      }
+    });
+    let smooth = png(6, 6, |x, y| {
+      if x > y {
+        [0, 0, 0, 255]
+      } else if x == y {
+        [128, 128, 128, 255]
+      } else {
+        [255, 255, 255, 255]
+      }
+    });
+
+    for (image1, image2) in [(&sharp, &smooth), (&smooth, &sharp)] {
+      let diff = diff(image1, image2, &DiffOpts::default()).unwrap();
+      assert_eq!(diff.compared, 64);
+      // The 28 pixels present in only one image as well as the seam
+      // pixels at (4,4) and (5,5), whose neighborhoods extend beyond
+      // the smaller image.
+      assert_eq!(diff.mismatched, 30);
+    }
+  }
+}
@@ -3,8 +3,47 @@
 
 //! A library for ~~scraping~~ shaving data from websites.
 
+mod action;
+mod block;
+mod cdp;
 mod client;
+mod command;
+mod console;
+mod devtools;
+mod diff;
+mod login;
+mod network;
+#[cfg(target_os = "linux")]
+mod process;
+mod proxy;
+mod screenshot;
+mod scroll;
+mod serve;
+mod stable;
 
+pub use action::Action;
+pub use block::BlockRules;
+pub use block::ResourceType;
+pub use cdp::ColorScheme;
+pub use cdp::DeviceMetrics;
+pub use cdp::EmulatedMedia;
+pub use cdp::Geolocation;
+pub use cdp::MediaType;
+pub use cdp::PdfOpts;
 pub use client::Builder;
 pub use client::Client;
 pub use client::ScreenshotOpts;
+pub use console::ConsoleMessage;
+pub use console::Level;
+pub use diff::diff;
+pub use diff::Diff;
+pub use diff::DiffOpts;
+pub use diff::Region;
+pub use login::Login;
+pub use network::NetworkLog;
+pub use network::Request;
+pub use proxy::Proxy;
+pub use screenshot::Bounds;
+pub use screenshot::Screenshot;
+pub use screenshot::Timings;
+pub use scroll::AutoScroll;
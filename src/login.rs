@@ -0,0 +1,153 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Authentication via login forms.
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use fantoccini::key::Key;
+use fantoccini::Client as WebdriverClient;
+use fantoccini::Locator;
+
+
+/// A description of how to log in via a form.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Login {
+  /// The URL of the page containing the login form.
+  pub url: String,
+  /// The selector of the user name input field.
+  pub username_selector: String,
+  /// The selector of the password input field.
+  pub password_selector: String,
+  /// The selector of the element to click to submit the form.
+  ///
+  /// If `None`, the form is submitted by pressing Enter in the password
+  /// field.
+  pub submit_selector: Option<String>,
+  /// The selector of an element that appears only once the login
+  /// succeeded.
+  pub success_selector: String,
+  /// The user name to log in with.
+  pub username: String,
+  /// The password to log in with.
+  pub password: String,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Login {
+  /// Fill in the input field identified by `selector` with `text`.
+  async fn fill(webdriver: &WebdriverClient, selector: &str, text: &str) -> Result<()> {
+    let element = webdriver
+      .find(Locator::Css(selector))
+      .await
+      .with_context(|| format!("failed to find `{selector}`"))?;
+    let () = element
+      .clear()
+      .await
+      .with_context(|| format!("failed to clear `{selector}`"))?;
+    let () = element
+      .send_keys(text)
+      .await
+      .with_context(|| format!("failed to type into `{selector}`"))?;
+    Ok(())
+  }
+
+  /// Log in using the provided WebDriver client.
+  pub(crate) async fn perform(&self, webdriver: &WebdriverClient) -> Result<()> {
+    let Self {
+      url,
+      username_selector,
+      password_selector,
+      submit_selector,
+      success_selector,
+      username,
+      password,
+      _non_exhaustive: (),
+    } = self;
+
+    let () = webdriver
+      .goto(url)
+      .await
+      .with_context(|| format!("failed to navigate to {url}"))?;
+    let () = Self::fill(webdriver, username_selector, username).await?;
+    let () = Self::fill(webdriver, password_selector, password).await?;
+
+    if let Some(submit_selector) = submit_selector {
+      let () = webdriver
+        .find(Locator::Css(submit_selector))
+        .await
+        .with_context(|| format!("failed to find `{submit_selector}`"))?
+        .click()
+        .await
+        .with_context(|| format!("failed to click `{submit_selector}`"))?;
+    } else {
+      let () = webdriver
+        .find(Locator::Css(password_selector))
+        .await
+        .with_context(|| format!("failed to find `{password_selector}`"))?
+        .send_keys(&Key::Enter)
+        .await
+        .context("failed to submit login form")?;
+    }
+
+    let _elem = webdriver
+      .wait()
+      .for_element(Locator::Css(success_selector))
+      .await
+      .with_context(|| format!("`{success_selector}` did not appear after submitting"))?;
+    Ok(())
+  }
+}
+
+impl Debug for Login {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    let Self {
+      url,
+      username_selector,
+      password_selector,
+      submit_selector,
+      success_selector,
+      username,
+      password: _,
+      _non_exhaustive: (),
+    } = self;
+
+    // Make sure to never leak the password into logs.
+    f.debug_struct("Login")
+      .field("url", url)
+      .field("username_selector", username_selector)
+      .field("password_selector", password_selector)
+      .field("submit_selector", submit_selector)
+      .field("success_selector", success_selector)
+      .field("username", username)
+      .field("password", &"<redacted>")
+      .finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Make sure that the password is not included in debug output.
+  #[test]
+  fn debug_redaction() {
+    let login = Login {
+      username: "alice".to_string(),
+      password: "hunter2".to_string(),
+      ..Default::default()
+    };
+    let debug = format!("{login:?}");
+    assert!(debug.contains("alice"), "{debug}");
+    assert!(!debug.contains("hunter2"), "{debug}");
+  }
+}
@@ -0,0 +1,131 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Functionality for finding and killing processes based on their
+//! command line.
+
+use std::ffi::OsStr;
+use std::fs::read;
+use std::fs::read_dir;
+use std::io;
+use std::os::unix::ffi::OsStrExt as _;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use libc::getpgid;
+use libc::getpgrp;
+use libc::kill;
+use libc::killpg;
+use libc::pid_t as Pid;
+use libc::SIGKILL;
+
+
+/// Check whether the process with the given ID has `arg` as one of its
+/// command line arguments.
+fn has_arg(pid: Pid, arg: &OsStr) -> io::Result<bool> {
+  let cmdline = read(format!("/proc/{pid}/cmdline"))?;
+  let found = cmdline
+    .split(|b| *b == b'\0')
+    .any(|cmd_arg| cmd_arg == arg.as_bytes());
+  Ok(found)
+}
+
+/// Find the IDs of all processes that have `arg` as one of their
+/// command line arguments.
+pub(crate) fn find_by_arg(arg: &OsStr) -> Result<Vec<Pid>> {
+  let mut pids = Vec::new();
+  let entries = read_dir("/proc").context("failed to read `/proc` directory")?;
+
+  for entry in entries {
+    let entry = entry.context("failed to read `/proc` directory entry")?;
+    let pid = if let Some(pid) = entry
+      .file_name()
+      .to_str()
+      .and_then(|name| name.parse::<Pid>().ok())
+    {
+      pid
+    } else {
+      continue
+    };
+
+    // Processes may vanish at any point and we may not have permission
+    // to inspect all of them. Neither case is of concern to us.
+    if let Ok(true) = has_arg(pid, arg) {
+      let () = pids.push(pid);
+    }
+  }
+  Ok(pids)
+}
+
+/// Kill all processes (as well as the process groups they lead) that
+/// have `arg` as one of their command line arguments.
+///
+/// Processes may not have vanished yet when this function returns.
+pub(crate) fn kill_by_arg(arg: &OsStr) -> Result<()> {
+  // SAFETY: `getpgrp` is always safe to call.
+  let own_pgid = unsafe { getpgrp() };
+
+  for pid in find_by_arg(arg)? {
+    // SAFETY: `getpgid` is always safe to call.
+    let pgid = unsafe { getpgid(pid) };
+    // Only take down entire process groups led by a matching process,
+    // and never our own.
+    if pgid == pid && pgid != own_pgid {
+      // SAFETY: `killpg` is always safe to call.
+      let _result = unsafe { killpg(pgid, SIGKILL) };
+    }
+    // SAFETY: `kill` is always safe to call.
+    let _result = unsafe { kill(pid, SIGKILL) };
+  }
+  Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::os::unix::process::CommandExt as _;
+  use std::os::unix::process::ExitStatusExt as _;
+  use std::process;
+  use std::process::Command;
+  use std::thread::sleep;
+  use std::time::Duration;
+
+
+  /// Check that we can find and kill a process based on a command line
+  /// argument.
+  #[test]
+  fn find_and_kill() {
+    let marker = format!("shave-test-marker-{}", process::id());
+    let marker = OsStr::new(&marker);
+    // NB: The trailing `true` prevents the shell from `exec`ing `sleep`
+    //     directly, which would make our marker disappear.
+    let mut child = Command::new("sh")
+      .arg("-c")
+      .arg("sleep 60; true")
+      .arg(marker)
+      .process_group(0)
+      .spawn()
+      .unwrap();
+
+    // The child may not have `exec`ed yet, so give it some time to
+    // show up.
+    let pids = loop {
+      let pids = find_by_arg(marker).unwrap();
+      if !pids.is_empty() {
+        break pids
+      }
+      let () = sleep(Duration::from_millis(1));
+    };
+    // NB: The shell may briefly have a forked copy of itself (and,
+    //     hence, with our marker) around before running `sleep`.
+    assert!(pids.contains(&(child.id() as Pid)), "{pids:?}");
+
+    let () = kill_by_arg(marker).unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(status.signal(), Some(SIGKILL));
+    assert_eq!(find_by_arg(marker).unwrap(), Vec::<Pid>::new());
+  }
+}
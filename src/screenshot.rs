@@ -0,0 +1,267 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::io::Cursor;
+use std::time::Duration;
+
+use png::Decoder;
+
+use serde_json::json;
+use serde_json::Value;
+
+
+/// Retrieve the width and height of a PNG image from its header.
+fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+  let reader = Decoder::new(Cursor::new(png)).read_info().ok()?;
+  let info = reader.info();
+  Some((info.width, info.height))
+}
+
+/// Convert a duration into fractional milliseconds.
+fn millis(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}
+
+
+/// A rectangular area of a page (e.g., the bounding box of an
+/// element), in CSS pixels relative to the top left corner of the page.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bounds {
+  /// The horizontal position of the area.
+  pub x: f64,
+  /// The vertical position of the area.
+  pub y: f64,
+  /// The width of the area.
+  pub width: f64,
+  /// The height of the area.
+  pub height: f64,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Bounds {
+  /// Grow the area by `padding` on each side, without extending it
+  /// beyond the top or left edge of the page.
+  pub(crate) fn pad(&self, padding: f64) -> Self {
+    let x = (self.x - padding).max(0.0);
+    let y = (self.y - padding).max(0.0);
+
+    Self {
+      x,
+      y,
+      width: self.x + self.width + padding - x,
+      height: self.y + self.height + padding - y,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+/// Timing information about a capture.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Timings {
+  /// The time it took to navigate to the page and for it to load.
+  pub navigation: Duration,
+  /// The time it took to take the actual screenshot.
+  pub capture: Duration,
+  /// The time the capture took overall.
+  pub total: Duration,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+
+/// A captured screenshot along with metadata about the page it was
+/// taken of.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Screenshot {
+  /// The screenshot in the form of a PNG image.
+  pub image: Vec<u8>,
+  /// The URL of the page, after following redirects.
+  pub url: String,
+  /// The HTTP status of the main document's response, if known.
+  ///
+  /// The status is only known if recording of network activity is
+  /// enabled (see [`Builder::set_record_network`][crate::Builder::set_record_network]).
+  pub status: Option<u16>,
+  /// The URLs and HTTP statuses of the redirects followed to arrive at
+  /// `url`, in order, if recording of network activity is enabled.
+  pub redirects: Vec<(String, u16)>,
+  /// The title of the page.
+  pub title: String,
+  /// The captured area of the page, if an element (including any
+  /// padding) or an explicit region was captured as opposed to the
+  /// viewport.
+  pub bounds: Option<Bounds>,
+  /// The width of the image, in pixels.
+  pub width: u32,
+  /// The height of the image, in pixels.
+  pub height: u32,
+  /// Timing information about the capture.
+  pub timings: Timings,
+  /// The results of evaluating
+  /// [`ScreenshotOpts::scripts`][crate::ScreenshotOpts::scripts], in
+  /// order.
+  pub script_results: Vec<Value>,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl Screenshot {
+  /// Create a `Screenshot` object for the given PNG image, with all
+  /// metadata but the image dimensions left empty.
+  pub(crate) fn new(image: Vec<u8>) -> Self {
+    let (width, height) = png_dimensions(&image).unwrap_or_default();
+
+    Self {
+      image,
+      url: String::new(),
+      status: None,
+      redirects: Vec::new(),
+      title: String::new(),
+      bounds: None,
+      width,
+      height,
+      timings: Timings::default(),
+      script_results: Vec::new(),
+      _non_exhaustive: (),
+    }
+  }
+
+  /// Convert the screenshot's metadata (that is, everything but the
+  /// image itself) into JSON.
+  pub fn to_json(&self) -> Value {
+    let Self {
+      image: _,
+      url,
+      status,
+      redirects,
+      title,
+      bounds,
+      width,
+      height,
+      timings,
+      script_results,
+      _non_exhaustive: (),
+    } = self;
+
+    let bounds = bounds.map(|bounds| {
+      json!({
+        "x": bounds.x,
+        "y": bounds.y,
+        "width": bounds.width,
+        "height": bounds.height,
+      })
+    });
+
+    let redirects = redirects
+      .iter()
+      .map(|(url, status)| json!({"url": url, "status": status}))
+      .collect::<Vec<_>>();
+
+    json!({
+      "url": url,
+      "status": status,
+      "redirects": redirects,
+      "title": title,
+      "bounds": bounds,
+      "width": width,
+      "height": height,
+      "timings": {
+        "navigation": millis(timings.navigation),
+        "capture": millis(timings.capture),
+        "total": millis(timings.total),
+      },
+      "script_results": script_results,
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// A 3x2 pixel PNG image.
+  const PNG: [u8; 73] = [
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x9d, 0x74, 0x66,
+    0x1a, 0x00, 0x00, 0x00, 0x10, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x60, 0x60, 0x60, 0xf8,
+    0x8f, 0x84, 0x11, 0x1c, 0x00, 0x44, 0xd5, 0x05, 0xfb, 0x7f, 0x0a, 0x70, 0x91, 0x00, 0x00, 0x00,
+    0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+  ];
+
+
+  /// Check that we can infer image dimensions from PNG data.
+  #[test]
+  fn dimensions() {
+    assert_eq!(png_dimensions(&PNG), Some((3, 2)));
+    assert_eq!(png_dimensions(&PNG[..20]), None);
+    assert_eq!(png_dimensions(b"GIF89a"), None);
+  }
+
+  /// Check that we pad areas as expected.
+  #[test]
+  fn padding() {
+    let bounds = Bounds {
+      x: 10.0,
+      y: 4.0,
+      width: 100.0,
+      height: 50.0,
+      _non_exhaustive: (),
+    };
+    let expected = Bounds {
+      x: 2.0,
+      y: 0.0,
+      width: 116.0,
+      height: 62.0,
+      _non_exhaustive: (),
+    };
+    assert_eq!(bounds.pad(8.0), expected);
+    assert_eq!(bounds.pad(0.0), bounds);
+  }
+
+  /// Make sure that we convert screenshot metadata into the expected
+  /// JSON.
+  #[test]
+  fn json_conversion() {
+    let screenshot = Screenshot {
+      url: "https://example.com/".to_string(),
+      status: Some(200),
+      redirects: vec![("http://example.com/".to_string(), 301)],
+      title: "Example".to_string(),
+      bounds: Some(Bounds {
+        x: 8.0,
+        y: 16.5,
+        width: 100.0,
+        height: 50.0,
+        _non_exhaustive: (),
+      }),
+      timings: Timings {
+        navigation: Duration::from_millis(250),
+        capture: Duration::from_millis(50),
+        total: Duration::from_millis(300),
+        ..Default::default()
+      },
+      script_results: vec![json!({"items": 3})],
+      ..Screenshot::new(PNG.to_vec())
+    };
+
+    let expected = json!({
+      "url": "https://example.com/",
+      "status": 200,
+      "redirects": [{"url": "http://example.com/", "status": 301}],
+      "title": "Example",
+      "bounds": {"x": 8.0, "y": 16.5, "width": 100.0, "height": 50.0},
+      "width": 3,
+      "height": 2,
+      "timings": {"navigation": 250.0, "capture": 50.0, "total": 300.0},
+      "script_results": [{"items": 3}],
+    });
+    assert_eq!(screenshot.to_json(), expected);
+  }
+}
@@ -0,0 +1,124 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Scrolling through pages to trigger lazy loading of content.
+
+use std::time::Duration;
+
+use serde_json::json;
+use serde_json::Value;
+
+
+/// Script scrolling down the page by a single increment, evaluating to
+/// whether it moved.
+///
+/// Arguments are the increment (or `null` for the viewport height) and
+/// the maximum height to scroll to, both in CSS pixels.
+pub(crate) const SCROLL_STEP_SCRIPT: &str = r#"
+  const [step, maxHeight] = arguments;
+  const scroller = document.scrollingElement || document.documentElement;
+  const height = Math.min(scroller.scrollHeight, maxHeight);
+  const y = window.scrollY;
+  if (y + window.innerHeight >= height) {
+    return false;
+  }
+
+  window.scrollTo(0, Math.min(y + (step || window.innerHeight), height - window.innerHeight));
+  return window.scrollY > y;
+"#;
+
+/// Script finishing up scrolling through the page, evaluating to a
+/// promise that resolves once done.
+///
+/// It waits for images in the area scrolled through to load, for at
+/// most five seconds, and scrolls back to the top.
+pub(crate) const SCROLL_FINISH_SCRIPT: &str = r#"
+  const sleep = (ms) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  return (async function() {
+    // Wait for images in the area scrolled through to finish loading,
+    // but don't let a single stalled one hold up the capture.
+    const bottom = window.scrollY + window.innerHeight;
+    const pending = Array.from(document.images)
+      .filter((img) => !img.complete && img.getBoundingClientRect().top + window.scrollY < bottom)
+      .map((img) => new Promise((resolve) => {
+        img.addEventListener("load", resolve, {once: true});
+        img.addEventListener("error", resolve, {once: true});
+      }));
+    await Promise.race([Promise.all(pending), sleep(5000)]);
+
+    window.scrollTo(0, 0);
+    await new Promise((resolve) => requestAnimationFrame(() => requestAnimationFrame(resolve)));
+  })();
+"#;
+
+
+/// A type encompassing options for scrolling through a page before
+/// capture, in order to trigger lazy loading of content.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AutoScroll {
+  /// The distance to scroll by in each increment, in CSS pixels.
+  ///
+  /// If `None`, the height of the viewport is used.
+  pub step: Option<usize>,
+  /// The time to wait after each increment.
+  ///
+  /// Each increment is performed by a separate script, so the delay
+  /// (and the number of increments) is only bounded by the overall
+  /// timeout of the capture.
+  pub delay: Duration,
+  /// The maximum height to scroll to, in CSS pixels.
+  ///
+  /// Pages that load more content whenever the bottom is reached
+  /// would otherwise be scrolled through indefinitely.
+  pub max_height: usize,
+  /// The type is non-exhaustive and open to extension.
+  #[doc(hidden)]
+  pub _non_exhaustive: (),
+}
+
+impl AutoScroll {
+  /// Create the arguments to pass to [`SCROLL_STEP_SCRIPT`].
+  pub(crate) fn to_args(&self) -> Vec<Value> {
+    let Self {
+      step,
+      delay: _,
+      max_height,
+      _non_exhaustive: (),
+    } = self;
+
+    vec![json!(step), json!(max_height)]
+  }
+}
+
+impl Default for AutoScroll {
+  fn default() -> Self {
+    Self {
+      step: None,
+      delay: Duration::from_millis(100),
+      max_height: 20_000,
+      _non_exhaustive: (),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+
+  /// Check that we create the expected script arguments.
+  #[test]
+  fn script_args() {
+    let scroll = AutoScroll::default();
+    assert_eq!(scroll.to_args(), vec![json!(null), json!(20_000)]);
+
+    let scroll = AutoScroll {
+      step: Some(500),
+      delay: Duration::from_secs(1),
+      ..Default::default()
+    };
+    assert_eq!(scroll.to_args(), vec![json!(500), json!(20_000)]);
+  }
+}
@@ -0,0 +1,470 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A minimal HTTP server for serving a local directory to the browser.
+
+use std::convert::Infallible;
+use std::io;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::net::TcpListener as StdTcpListener;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::from_utf8;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use futures_util::stream::unfold;
+
+use http::header::CACHE_CONTROL;
+use http::header::CONTENT_LENGTH;
+use http::header::CONTENT_TYPE;
+use http::header::LOCATION;
+use http::HeaderName;
+use http::HeaderValue;
+use http::Method;
+use http::Request;
+use http::Response;
+use http::StatusCode;
+
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::BodyExt as _;
+use http_body_util::Empty;
+use http_body_util::StreamBody;
+
+use hyper::body::Bytes;
+use hyper::body::Frame;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+
+use hyper_util::rt::TokioIo;
+use hyper_util::rt::TokioTimer;
+
+use tokio::fs::File;
+use tokio::io::AsyncReadExt as _;
+use tokio::net::TcpListener;
+use tokio::runtime::Handle;
+use tokio::spawn;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+
+/// The timeout for reading the head of a request.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+/// The maximum number of connections that we serve concurrently.
+const MAX_CONNECTIONS: usize = 64;
+/// The size of the chunks in which file contents are sent.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+
+/// The type of response bodies.
+type Body = UnsyncBoxBody<Bytes, io::Error>;
+
+
+/// Retrieve the MIME type to report for a file with the given path.
+fn content_type(path: &Path) -> &'static str {
+  let ext = path
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .map(str::to_ascii_lowercase)
+    .unwrap_or_default();
+
+  match ext.as_str() {
+    "html" | "htm" => "text/html; charset=utf-8",
+    "css" => "text/css; charset=utf-8",
+    "js" | "mjs" => "text/javascript; charset=utf-8",
+    "json" | "map" => "application/json",
+    "txt" => "text/plain; charset=utf-8",
+    "csv" => "text/csv; charset=utf-8",
+    "xml" => "application/xml",
+    "svg" => "image/svg+xml",
+    "png" => "image/png",
+    "jpg" | "jpeg" => "image/jpeg",
+    "gif" => "image/gif",
+    "webp" => "image/webp",
+    "avif" => "image/avif",
+    "ico" => "image/x-icon",
+    "woff" => "font/woff",
+    "woff2" => "font/woff2",
+    "ttf" => "font/ttf",
+    "otf" => "font/otf",
+    "wasm" => "application/wasm",
+    "pdf" => "application/pdf",
+    "mp3" => "audio/mpeg",
+    "mp4" => "video/mp4",
+    "webm" => "video/webm",
+    _ => "application/octet-stream",
+  }
+}
+
+/// Decode a percent-encoded URL path component.
+///
+/// Malformed escape sequences are passed through verbatim.
+fn percent_decode(s: &str) -> Vec<u8> {
+  let bytes = s.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut idx = 0;
+
+  while idx < bytes.len() {
+    let escaped = (bytes[idx] == b'%')
+      .then(|| bytes.get(idx + 1..idx + 3))
+      .flatten()
+      .and_then(|hex| u8::from_str_radix(from_utf8(hex).ok()?, 16).ok());
+
+    if let Some(byte) = escaped {
+      let () = decoded.push(byte);
+      idx += 3;
+    } else {
+      let () = decoded.push(bytes[idx]);
+      idx += 1;
+    }
+  }
+  decoded
+}
+
+/// Map a request path onto a file system path below `root`.
+///
+/// `None` is returned for paths attempting to escape `root`.
+fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+  let path = path.split(['?', '#']).next().unwrap_or_default();
+  let mut resolved = root.to_path_buf();
+
+  for segment in path.split('/') {
+    let segment = String::from_utf8(percent_decode(segment)).ok()?;
+    match segment.as_str() {
+      "" | "." => (),
+      ".." => return None,
+      _ if segment.contains(['/', '\\', '\0']) => return None,
+      _ => resolved.push(segment),
+    }
+  }
+  Some(resolved)
+}
+
+/// Resolve symbolic links in `file`, making sure that the result is
+/// still located below the canonical `root`.
+///
+/// On failure, the HTTP status to respond with is returned.
+fn confine(root: &Path, file: &Path) -> Result<PathBuf, StatusCode> {
+  match file.canonicalize() {
+    Ok(file) if file.starts_with(root) => Ok(file),
+    Ok(_) => Err(StatusCode::FORBIDDEN),
+    Err(_) => Err(StatusCode::NOT_FOUND),
+  }
+}
+
+/// Create a body streaming the contents of the provided file.
+fn file_body(file: File) -> Body {
+  let chunks = unfold(file, |mut file| async move {
+    let mut buffer = vec![0; CHUNK_SIZE];
+    match file.read(&mut buffer).await {
+      Ok(0) => None,
+      Ok(count) => {
+        let () = buffer.truncate(count);
+        Some((Ok(Frame::data(Bytes::from(buffer))), file))
+      },
+      Err(err) => Some((Err(err), file)),
+    }
+  });
+  StreamBody::new(chunks).boxed_unsync()
+}
+
+/// Create a response with the given status, headers, and body.
+fn response(
+  status: StatusCode,
+  headers: &[(HeaderName, HeaderValue)],
+  body: Body,
+) -> Response<Body> {
+  let mut response = Response::new(body);
+  *response.status_mut() = status;
+  let () = response.headers_mut().extend(headers.iter().cloned());
+  let _prev = response
+    .headers_mut()
+    .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+  response
+}
+
+/// Create an empty body.
+fn empty_body() -> Body {
+  Empty::new().map_err(|never| match never {}).boxed_unsync()
+}
+
+/// Create a response with the given status and an empty body.
+fn empty(status: StatusCode) -> Response<Body> {
+  response(status, &[], empty_body())
+}
+
+/// Handle a single request, serving files below `root`.
+async fn handle(root: &Path, request: Request<Incoming>) -> Response<Body> {
+  let include_body = match *request.method() {
+    Method::GET => true,
+    Method::HEAD => false,
+    _ => return empty(StatusCode::METHOD_NOT_ALLOWED),
+  };
+
+  let path = request.uri().path();
+  let file = match resolve(root, path)
+    .ok_or(StatusCode::FORBIDDEN)
+    .and_then(|file| confine(root, &file))
+  {
+    Ok(file) => file,
+    Err(status) => return empty(status),
+  };
+
+  let file = if file.is_dir() {
+    // Relative URLs in an index document are resolved against the
+    // directory, so make sure that it is addressed with trailing
+    // slash.
+    if !path.ends_with('/') {
+      let location = match request.uri().query() {
+        Some(query) => format!("{path}/?{query}"),
+        None => format!("{path}/"),
+      };
+      let location = match HeaderValue::try_from(location) {
+        Ok(location) => location,
+        Err(_) => return empty(StatusCode::BAD_REQUEST),
+      };
+      return response(
+        StatusCode::MOVED_PERMANENTLY,
+        &[(LOCATION, location)],
+        empty_body(),
+      )
+    }
+    match confine(root, &file.join("index.html")) {
+      Ok(file) => file,
+      Err(status) => return empty(status),
+    }
+  } else {
+    file
+  };
+
+  let (data, len) = match File::open(&file).await {
+    Ok(data) => match data.metadata().await {
+      Ok(metadata) => (data, metadata.len()),
+      Err(_err) => return empty(StatusCode::NOT_FOUND),
+    },
+    Err(_err) => return empty(StatusCode::NOT_FOUND),
+  };
+
+  let headers = [
+    (CONTENT_TYPE, HeaderValue::from_static(content_type(&file))),
+    (CONTENT_LENGTH, HeaderValue::from(len)),
+  ];
+  let body = if include_body {
+    file_body(data)
+  } else {
+    empty_body()
+  };
+  response(StatusCode::OK, &headers, body)
+}
+
+/// Accept connections on `listener` and serve files below `root` on
+/// them.
+async fn serve(listener: TcpListener, root: PathBuf) {
+  let root = Arc::new(root);
+  let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+
+  loop {
+    // Wait for a connection to finish once we serve the maximum
+    // number of them, instead of accepting more.
+    let permit = match Arc::clone(&connections).acquire_owned().await {
+      Ok(permit) => permit,
+      Err(_closed) => break,
+    };
+    let stream = match listener.accept().await {
+      Ok((stream, _addr)) => stream,
+      Err(_err) => continue,
+    };
+
+    let root = Arc::clone(&root);
+    let service = service_fn(move |request| {
+      let root = Arc::clone(&root);
+      async move { Ok::<_, Infallible>(handle(&root, request).await) }
+    });
+    let _handle = spawn(async move {
+      let _result = http1::Builder::new()
+        .timer(TokioTimer::new())
+        .header_read_timeout(IO_TIMEOUT)
+        .serve_connection(TokioIo::new(stream), service)
+        .await;
+      let () = drop(permit);
+    });
+  }
+}
+
+
+/// An HTTP server serving the contents of a local directory on an
+/// ephemeral loopback port.
+///
+/// The server runs as a task on the current Tokio runtime.
+#[derive(Debug)]
+pub(crate) struct Server {
+  /// The address the server is listening on.
+  addr: SocketAddr,
+  /// The task accepting connections.
+  task: JoinHandle<()>,
+}
+
+impl Server {
+  /// Start serving the contents of the provided directory.
+  pub(crate) fn new(dir: &Path) -> Result<Self> {
+    let root = dir
+      .canonicalize()
+      .with_context(|| format!("failed to canonicalize `{}`", dir.display()))?;
+    let runtime = Handle::try_current().context("serving a directory requires a Tokio runtime")?;
+    let listener = StdTcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+      .context("failed to bind HTTP server to loopback address")?;
+    let addr = listener
+      .local_addr()
+      .context("failed to retrieve HTTP server address")?;
+    let () = listener
+      .set_nonblocking(true)
+      .context("failed to make HTTP server socket non-blocking")?;
+
+    let _guard = runtime.enter();
+    let listener =
+      TcpListener::from_std(listener).context("failed to register HTTP server socket")?;
+    let task = spawn(serve(listener, root));
+    Ok(Self { addr, task })
+  }
+
+  /// Retrieve the URL of the provided absolute path on the server.
+  pub(crate) fn url(&self, path: &str) -> String {
+    format!("http://{}{path}", self.addr)
+  }
+}
+
+impl Drop for Server {
+  fn drop(&mut self) {
+    let () = self.task.abort();
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::fs::create_dir;
+  use std::fs::write;
+  use std::os::unix::fs::symlink;
+
+  use tempfile::TempDir;
+
+  use tokio::io::AsyncWriteExt as _;
+  use tokio::net::TcpStream;
+
+
+  /// Issue a raw HTTP request to the server and return the response.
+  async fn request(server: &Server, method: &str, path: &str) -> String {
+    let mut stream = TcpStream::connect(server.addr).await.unwrap();
+    let request =
+      format!("{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    let () = stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    let _count = stream.read_to_string(&mut response).await.unwrap();
+    response
+  }
+
+
+  /// Check that we decode percent-encoded path components.
+  #[test]
+  fn percent_decoding() {
+    assert_eq!(percent_decode("a%20b"), b"a b");
+    assert_eq!(percent_decode("%2e%2E"), b"..");
+    assert_eq!(percent_decode("100%"), b"100%");
+    assert_eq!(percent_decode("%zz"), b"%zz");
+  }
+
+  /// Make sure that request paths can't escape the root directory.
+  #[test]
+  fn path_resolution() {
+    let root = Path::new("/srv");
+    assert_eq!(
+      resolve(root, "/a/./b%20c.html?x=1"),
+      Some(PathBuf::from("/srv/a/b c.html"))
+    );
+    assert_eq!(resolve(root, "/"), Some(PathBuf::from("/srv")));
+    assert_eq!(resolve(root, "/../etc/passwd"), None);
+    assert_eq!(resolve(root, "/%2e%2e/etc/passwd"), None);
+    assert_eq!(resolve(root, "/a%2F..%2F..%2Fetc"), None);
+  }
+
+  /// Check that we can serve files from a directory.
+  #[tokio::test]
+  async fn serving() {
+    let dir = TempDir::new().unwrap();
+    let () = write(dir.path().join("index.html"), "<p>index</p>").unwrap();
+    let () = create_dir(dir.path().join("sub")).unwrap();
+    let () = write(dir.path().join("sub").join("app.mjs"), "export {}").unwrap();
+    let () = write(dir.path().join("sub").join("index.html"), "<p>sub</p>").unwrap();
+
+    let server = Server::new(dir.path()).unwrap();
+    assert!(server.url("/index.html").starts_with("http://127.0.0.1:"));
+
+    let response = request(&server, "GET", "/").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+    assert!(response.contains("content-type: text/html; charset=utf-8\r\n"));
+    assert!(response.ends_with("\r\n\r\n<p>index</p>"));
+
+    let response = request(&server, "GET", "/sub/app.mjs").await;
+    assert!(response.contains("content-type: text/javascript; charset=utf-8\r\n"));
+    assert!(response.ends_with("\r\n\r\nexport {}"));
+
+    let response = request(&server, "HEAD", "/sub/app.mjs").await;
+    assert!(response.contains("content-length: 9\r\n"));
+    assert!(response.ends_with("\r\n\r\n"));
+
+    let response = request(&server, "GET", "/sub?x=1").await;
+    assert!(response.starts_with("HTTP/1.1 301 "), "{response}");
+    assert!(response.contains("location: /sub/?x=1\r\n"));
+
+    let response = request(&server, "GET", "/missing.html").await;
+    assert!(response.starts_with("HTTP/1.1 404 "), "{response}");
+
+    let response = request(&server, "GET", "/../index.html").await;
+    assert!(response.starts_with("HTTP/1.1 403 "), "{response}");
+
+    let response = request(&server, "POST", "/index.html").await;
+    assert!(response.starts_with("HTTP/1.1 405 "), "{response}");
+  }
+
+  /// Make sure that symbolic links can't be used to escape the root
+  /// directory.
+  #[tokio::test]
+  async fn symlink_escape() {
+    let outside = TempDir::new().unwrap();
+    let () = write(outside.path().join("secret.txt"), "secret").unwrap();
+    let () = write(outside.path().join("index.html"), "secret").unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let () = write(dir.path().join("a.txt"), "a").unwrap();
+    let () = symlink(dir.path().join("a.txt"), dir.path().join("b.txt")).unwrap();
+    let () = symlink(
+      outside.path().join("secret.txt"),
+      dir.path().join("secret.txt"),
+    )
+    .unwrap();
+    let () = symlink(outside.path(), dir.path().join("out")).unwrap();
+    let () = create_dir(dir.path().join("sub")).unwrap();
+    let () = symlink(
+      outside.path().join("index.html"),
+      dir.path().join("sub").join("index.html"),
+    )
+    .unwrap();
+
+    let server = Server::new(dir.path()).unwrap();
+
+    let response = request(&server, "GET", "/b.txt").await;
+    assert!(response.ends_with("\r\n\r\na"), "{response}");
+
+    for path in ["/secret.txt", "/out/", "/out/secret.txt", "/sub/"] {
+      let response = request(&server, "GET", path).await;
+      assert!(response.starts_with("HTTP/1.1 403 "), "{path}: {response}");
+    }
+  }
+}
@@ -0,0 +1,75 @@
+// Copyright (C) 2026 Daniel Mueller <deso@posteo.net>
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Scripts for making page rendering deterministic.
+
+
+/// Script freezing sources of non-determinism available to page
+/// scripts, to be evaluated before any of them run.
+///
+/// `Date` starts out at a fixed point in time (2024-01-01T00:00:00Z)
+/// and advances by one millisecond whenever the current time is
+/// queried, so that it is deterministic but still monotonic.
+/// `Math.random` is replaced with a generator using a fixed seed.
+pub(crate) const FREEZE_SCRIPT: &str = r#"
+  (function() {
+    let time = 1704067200000;
+    const now = () => time++;
+    const OrigDate = Date;
+    function FrozenDate(...args) {
+      if (!new.target) {
+        return new OrigDate(now()).toString();
+      }
+      return new OrigDate(...(args.length === 0 ? [now()] : args));
+    }
+    FrozenDate.prototype = OrigDate.prototype;
+    FrozenDate.now = now;
+    FrozenDate.parse = OrigDate.parse;
+    FrozenDate.UTC = OrigDate.UTC;
+    window.Date = FrozenDate;
+
+    // Mulberry32, seeded with a fixed value.
+    let seed = 0x2f6b3a1d;
+    Math.random = function() {
+      seed = (seed + 0x6d2b79f5) | 0;
+      let t = Math.imul(seed ^ (seed >>> 15), 1 | seed);
+      t = (t + Math.imul(t ^ (t >>> 7), 61 | t)) ^ t;
+      return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+    };
+  })();
+"#;
+
+/// Script settling a loaded page for capture, evaluating to a promise
+/// that resolves once done.
+///
+/// CSS animations and transitions are disabled, the text caret is
+/// hidden, videos are paused at their beginning, and web fonts are
+/// awaited.
+pub(crate) const SETTLE_SCRIPT: &str = r#"
+  (async function() {
+    const style = document.createElement("style");
+    style.setAttribute("data-shave-stable", "");
+    style.textContent = `
+      *, *::before, *::after {
+        animation: none !important;
+        transition: none !important;
+        caret-color: transparent !important;
+        scroll-behavior: auto !important;
+      }
+    `;
+    document.documentElement.appendChild(style);
+
+    for (const video of document.querySelectorAll("video")) {
+      video.pause();
+      video.currentTime = 0;
+    }
+
+    if (document.activeElement instanceof HTMLElement) {
+      document.activeElement.blur();
+    }
+
+    await document.fonts.ready;
+    // Give the browser a chance to render the changes.
+    await new Promise((resolve) => requestAnimationFrame(() => requestAnimationFrame(resolve)));
+  })()
+"#;